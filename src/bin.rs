@@ -1,9 +1,15 @@
-use std::{env, io};
+use std::{env, fs, io};
 
-use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, TimeDelta, Utc};
-use clockedin_utils::clockedin_service::{
-    ClockedInService, ClockedInServiceError, EXPECTED_OVERTIME_WORK_JOURNEY_TIME_DELTA,
-    EXPECTED_WORK_JOURNEY_TIME_DELTA,
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use clockedin_utils::{
+    clock::FixedClock,
+    clockedin_service::{
+        ClockedInService, ClockedInServiceError, ComplianceAction, EnforcementMode, PunchOutcome,
+        StaleJourneyRecovery,
+    },
+    policy::PolicyPreset,
+    recommendations::{LeaveLimit, LeaveTarget},
+    violations::Violation,
 };
 use colored::Colorize;
 
@@ -13,6 +19,7 @@ pub enum MainProgramOptions {
     ClockOut = b'1',
     ClockOutAndEndDay = b'2',
     ClockOutAndEndWeek = b'3',
+    Punch = b'4',
     Invalid,
 }
 
@@ -23,6 +30,7 @@ impl MainProgramOptions {
             '1' => Self::ClockOut,
             '2' => Self::ClockOutAndEndDay,
             '3' => Self::ClockOutAndEndWeek,
+            '4' => Self::Punch,
             _ => Self::Invalid,
         }
     }
@@ -30,19 +38,43 @@ impl MainProgramOptions {
 
 fn main() {
     let raw_args: Vec<String> = env::args().collect();
-    let args: Vec<String> = raw_args.into_iter().skip(1).collect();
+    let mut args: Vec<String> = raw_args.into_iter().skip(1).collect();
     let mut clockedin_service = prologue();
+    if let Some(position) = args.iter().position(|arg| arg == "--now") {
+        let now = args
+            .get(position + 1)
+            .map(|value| parse_date_time(value))
+            .expect("Incorrect program usage. Program usage example: ./clockin --now \"2024-07-10 18:00\" view");
+        clockedin_service.set_clock(Box::new(FixedClock::new(now)));
+        args.drain(position..position + 2);
+    }
+    if args.first().is_some_and(|command| command == "check") {
+        check_integrity(&mut clockedin_service, args.get(1));
+        epilogue(&clockedin_service);
+        return;
+    }
+    let now = clockedin_service.now();
+    let is_interactive = args.first().is_none_or(|command| {
+        matches!(
+            command.as_str(),
+            "in" | "out" | "out_day" | "out_week" | "punch"
+        )
+    });
+    if is_interactive {
+        recover_stale_journey(&mut clockedin_service, now);
+    }
     let current_delta = match clockedin_service.worked_delta_until_today() {
         Ok(delta) => delta,
-        Err(err) => {
-            panic!("Error occurred: {}", err);
-        }
+        Err(err) => panic_epilogue(&clockedin_service, err),
     };
 
     if !args.is_empty() {
-        let now_date = chrono::offset::Utc::now().date_naive();
+        let now_date = now.date_naive();
         let command = args.first().expect("Impossible to happen.");
-        let time_string = if command != "view" {
+        let time_string = if matches!(
+            command.as_str(),
+            "in" | "out" | "out_day" | "out_week" | "punch"
+        ) {
             args.get(1)
                 .expect("Incorrect program usage. Program usage example: ./clockin in 10:35")
         } else {
@@ -54,38 +86,69 @@ fn main() {
             DateTime::from_naive_utc_and_offset(NaiveDateTime::new(now_date, clockin_time), Utc);
 
         if command == "in" {
-            match clockedin_service.clock_in(clockin_date_time) {
-                Ok(_) => (),
-                Err(err) => {
-                    panic_epilogue(&clockedin_service, err);
-                }
-            }
+            clock_in_with_confirmation(&mut clockedin_service, clockin_date_time);
         } else if command == "out" {
-            match clockedin_service.clock_out(clockin_date_time) {
+            match perform_checked(
+                &mut clockedin_service,
+                ComplianceAction::ClockOut,
+                clockin_date_time,
+                |service| service.clock_out(clockin_date_time),
+            ) {
                 Ok(_) => (),
                 Err(err) => panic_epilogue(&clockedin_service, err),
             }
         } else if command == "out_day" {
-            match clockedin_service.clock_out_and_end_work_day(clockin_date_time) {
+            match perform_checked(
+                &mut clockedin_service,
+                ComplianceAction::EndDay,
+                clockin_date_time,
+                |service| service.clock_out_and_end_work_day(clockin_date_time),
+            ) {
                 Ok(_) => (),
                 Err(err) => panic_epilogue(&clockedin_service, err),
             }
         } else if command == "out_week" {
-            match clockedin_service.clock_out_and_end_work_week(clockin_date_time) {
+            match perform_checked(
+                &mut clockedin_service,
+                ComplianceAction::EndDay,
+                clockin_date_time,
+                |service| service.clock_out_and_end_work_week(clockin_date_time),
+            ) {
                 Ok(_) => (),
                 Err(err) => panic_epilogue(&clockedin_service, err),
             }
-        } else if command == "view" {
+        } else if command == "punch" {
+            punch_with_confirmation(&mut clockedin_service, clockin_date_time);
+        } else if command == "import" {
+            import_punches(&mut clockedin_service, args.get(1));
+        } else if command == "justify" {
+            justify_violation(&mut clockedin_service, args.get(1));
+        } else if command == "report" {
+            print_compliance_report(&clockedin_service, &args[1..]);
+            epilogue(&clockedin_service);
+            return;
+        } else if command == "set" {
+            apply_setting(&mut clockedin_service, args.get(1), args.get(2));
+        } else if command == "close" {
+            close_period(&mut clockedin_service, &args[1..]);
+        } else if command == "balance" {
+            record_balance_entry(&mut clockedin_service, &args[1..]);
+        } else if command == "recompute" {
+            recompute_registry(&mut clockedin_service);
+        } else if command == "policy_from" {
+            add_policy_version(&mut clockedin_service, args.get(1), args.get(2));
+        } else if command == "view" || command == "premiums" {
         }
 
         let current_delta = match clockedin_service.worked_delta_until_today() {
             Ok(delta) => delta,
-            Err(err) => {
-                panic!("Error occurred: {}", err);
-            }
+            Err(err) => panic_epilogue(&clockedin_service, err),
         };
 
         display_general_information(&clockedin_service, current_delta);
+        if command == "premiums" {
+            display_premium_information(&clockedin_service, now_date);
+        }
     } else {
         loop {
             display_general_information(&clockedin_service, current_delta);
@@ -93,42 +156,57 @@ fn main() {
             println!("1. ClockOut");
             println!("2. ClockOut and end Day");
             println!("3. ClockOut and end Week");
+            println!("4. Punch");
             let mut buffer = String::new();
             io::stdin().read_line(&mut buffer).unwrap();
 
-            let now = chrono::offset::Utc::now();
+            let now = clockedin_service.now();
             match buffer.chars().next() {
                 Some(current_char) => match MainProgramOptions::from(current_char) {
                     MainProgramOptions::ClockIn => {
-                        match clockedin_service.clock_in(now) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                panic_epilogue(&clockedin_service, err);
-                            }
-                        }
+                        clock_in_with_confirmation(&mut clockedin_service, now);
                         break;
                     }
                     MainProgramOptions::ClockOut => {
-                        match clockedin_service.clock_out(now) {
+                        match perform_checked(
+                            &mut clockedin_service,
+                            ComplianceAction::ClockOut,
+                            now,
+                            |service| service.clock_out(now),
+                        ) {
                             Ok(_) => (),
                             Err(err) => panic_epilogue(&clockedin_service, err),
                         }
                         break;
                     }
                     MainProgramOptions::ClockOutAndEndDay => {
-                        match clockedin_service.clock_out_and_end_work_day(now) {
+                        match perform_checked(
+                            &mut clockedin_service,
+                            ComplianceAction::EndDay,
+                            now,
+                            |service| service.clock_out_and_end_work_day(now),
+                        ) {
                             Ok(_) => (),
                             Err(err) => panic_epilogue(&clockedin_service, err),
                         }
                         break;
                     }
                     MainProgramOptions::ClockOutAndEndWeek => {
-                        match clockedin_service.clock_out_and_end_work_week(now) {
+                        match perform_checked(
+                            &mut clockedin_service,
+                            ComplianceAction::EndDay,
+                            now,
+                            |service| service.clock_out_and_end_work_week(now),
+                        ) {
                             Ok(_) => (),
                             Err(err) => panic_epilogue(&clockedin_service, err),
                         }
                         break;
                     }
+                    MainProgramOptions::Punch => {
+                        punch_with_confirmation(&mut clockedin_service, now);
+                        break;
+                    }
                     MainProgramOptions::Invalid => continue,
                 },
                 None => todo!(),
@@ -139,6 +217,453 @@ fn main() {
     epilogue(&clockedin_service);
 }
 
+fn clock_in_with_confirmation(clockedin_service: &mut ClockedInService, time: DateTime<Utc>) {
+    match perform_checked(
+        clockedin_service,
+        ComplianceAction::ClockIn,
+        time,
+        |service| service.clock_in(time),
+    ) {
+        Ok(_) => (),
+        Err(ClockedInServiceError::RolloverConfirmationRequired(pending_date)) => {
+            let question = format!(
+                "Work from {} is still open. Close it and clock in? [y/N]",
+                pending_date
+            );
+            if !confirm(&question) {
+                return;
+            }
+            let result = clockedin_service
+                .roll_over_pending_work(time)
+                .and_then(|_| {
+                    perform_checked(
+                        clockedin_service,
+                        ComplianceAction::ClockIn,
+                        time,
+                        |service| service.clock_in(time),
+                    )
+                });
+            if let Err(err) = result {
+                panic_epilogue(clockedin_service, err);
+            }
+        }
+        Err(err) => panic_epilogue(clockedin_service, err),
+    }
+}
+
+fn punch_with_confirmation(clockedin_service: &mut ClockedInService, time: DateTime<Utc>) {
+    let action = if clockedin_service.is_clocked_in() {
+        ComplianceAction::ClockOut
+    } else {
+        ComplianceAction::ClockIn
+    };
+    match perform_checked(clockedin_service, action, time, |service| {
+        service.punch(time)
+    }) {
+        Ok(PunchOutcome::ClockedIn) => println!("{}", "Clocked in.".bright_blue().bold()),
+        Ok(PunchOutcome::ClockedOut) => println!("{}", "Clocked out.".bright_blue().bold()),
+        Err(ClockedInServiceError::RolloverConfirmationRequired(_)) => {
+            clock_in_with_confirmation(clockedin_service, time)
+        }
+        Err(err) => panic_epilogue(clockedin_service, err),
+    }
+}
+
+fn import_punches(clockedin_service: &mut ClockedInService, file_name: Option<&String>) {
+    let file_name = file_name
+        .expect("Incorrect program usage. Program usage example: ./clockin import punches.txt");
+    let content = fs::read_to_string(file_name).expect("Error occurred during punch file reading");
+    let punches: Vec<DateTime<Utc>> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_date_time)
+        .collect();
+
+    let proposal = clockedin_service.propose_punch_import(&punches);
+    println!("{}", "Proposed work days:".bright_blue());
+    print!("{}", proposal);
+
+    if !confirm("Commit the reviewed days into the registry? [y/N]") {
+        return;
+    }
+    match clockedin_service.commit_punch_import(&proposal) {
+        Ok(skipped_days) => {
+            for skipped_day in skipped_days {
+                println!(
+                    "{} {}",
+                    "Skipped, needs review:".bright_yellow().bold(),
+                    skipped_day
+                );
+            }
+        }
+        Err(err) => panic_epilogue(clockedin_service, err),
+    }
+}
+
+fn close_period(clockedin_service: &mut ClockedInService, args: &[String]) {
+    let closed_on = args
+        .first()
+        .map(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+        .expect("Incorrect program usage. Program usage example: ./clockin close 2024-06-30 First semester")
+        .expect("Error occurred during date parsing");
+    if !confirm(&format!(
+        "Close the period until {} and settle its balance? [y/N]",
+        closed_on
+    )) {
+        return;
+    }
+
+    match clockedin_service.close_period(closed_on, args[1..].join(" ")) {
+        Ok(settlement) => println!("{} {}", "Period closed:".bright_blue(), settlement),
+        Err(err) => panic_epilogue(clockedin_service, err),
+    }
+}
+
+fn record_balance_entry(clockedin_service: &mut ClockedInService, args: &[String]) {
+    if args.is_empty() {
+        return;
+    }
+    let usage = "Incorrect program usage. Program usage example: ./clockin balance adjust 2024-07-31 -10:30 Payout";
+    let date = NaiveDate::parse_from_str(args.get(1).expect(usage), "%Y-%m-%d")
+        .expect("Error occurred during date parsing");
+    let credit = parse_signed_hours(args.get(2).expect(usage));
+    let note = args[3..].join(" ");
+
+    let result = match args[0].as_str() {
+        "open" => clockedin_service.set_opening_balance(date, credit, note),
+        "adjust" => clockedin_service.add_balance_adjustment(date, credit, note),
+        _ => panic!("{}", usage),
+    };
+    if let Err(err) = result {
+        panic_epilogue(clockedin_service, err);
+    }
+}
+
+/// Parses `[-]H:MM` into a signed duration.
+fn parse_signed_hours(value: &str) -> TimeDelta {
+    let (sign, hours_minutes) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value),
+    };
+    let (hours, minutes) = hours_minutes
+        .split_once(':')
+        .and_then(|(hours, minutes)| {
+            Some((hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?))
+        })
+        .expect("Error occurred during hours parsing");
+    (TimeDelta::hours(hours) + TimeDelta::minutes(minutes)) * sign
+}
+
+fn check_integrity(clockedin_service: &mut ClockedInService, option: Option<&String>) {
+    let repair = match option.map(String::as_str) {
+        None => false,
+        Some("--repair") => true,
+        Some(_) => {
+            panic!("Incorrect program usage. Program usage example: ./clockin check --repair")
+        }
+    };
+    let issues = clockedin_service.check_integrity();
+    if issues.is_empty() {
+        println!("{}", "No problems found.".bright_blue());
+        return;
+    }
+
+    for issue in &issues {
+        println!("{} {}", "!".bright_yellow().bold(), issue);
+    }
+    if repair {
+        let repaired = clockedin_service.repair_integrity();
+        println!("{} {}", "Repaired problems:".bright_blue(), repaired);
+    } else if issues.iter().any(|issue| issue.is_repairable()) {
+        println!(
+            "{}",
+            "Run with --repair to fix the repairable problems.".bright_blue()
+        );
+    }
+}
+
+fn recompute_registry(clockedin_service: &mut ClockedInService) {
+    let recomputation = clockedin_service.propose_recompute();
+    if recomputation.is_empty() {
+        println!(
+            "{}",
+            "Recorded days already match the current rules.".bright_blue()
+        );
+        return;
+    }
+
+    println!("{}", "Recomputed work days:".bright_blue());
+    print!("{}", recomputation);
+    if confirm("Replace the recorded days with the recomputed ones? [y/N]") {
+        clockedin_service.commit_recompute(recomputation);
+    }
+}
+
+fn apply_setting(
+    clockedin_service: &mut ClockedInService,
+    key: Option<&String>,
+    value: Option<&String>,
+) {
+    let mut settings = clockedin_service.settings().clone();
+    let mut policy = clockedin_service.policy().clone();
+    let value = value.expect("Incorrect program usage. Program usage example: ./clockin set require_rollover_confirmation true");
+
+    match key.map(String::as_str) {
+        Some("require_rollover_confirmation") => {
+            settings.require_rollover_confirmation = value
+                .parse()
+                .expect("Error occurred during setting parsing");
+        }
+        Some("stale_journey_limit_hours") => {
+            let hours: i64 = value
+                .parse()
+                .expect("Error occurred during setting parsing");
+            settings.stale_journey_limit = TimeDelta::hours(hours);
+        }
+        Some("enforcement_mode") => {
+            settings.enforcement_mode = match value.as_str() {
+                "advisory" => EnforcementMode::Advisory,
+                "confirm" => EnforcementMode::Confirm,
+                "strict" => EnforcementMode::Strict,
+                _ => panic!("Unknown enforcement mode. Available modes: advisory, confirm, strict"),
+            };
+        }
+        Some("policy_preset") => {
+            let clock_time = policy.day.clock_time.clone();
+            policy = PolicyPreset::from_name(value)
+                .expect("Unknown policy preset. Available presets: clt, eu_wtd, us_flsa")
+                .policy();
+            policy.day.clock_time = clock_time;
+        }
+        Some("rounding_minutes") => {
+            policy.day.clock_time.rounding_step = parse_minutes(value);
+        }
+        Some("punch_tolerance_minutes") => {
+            policy.day.clock_time.punch_tolerance = parse_minutes(value);
+        }
+        Some("daily_tolerance_minutes") => {
+            policy.day.clock_time.daily_tolerance = parse_minutes(value);
+        }
+        Some("scheduled_punches") => {
+            policy.day.clock_time.scheduled_punches = value
+                .split(',')
+                .filter(|time| !time.is_empty())
+                .map(|time| {
+                    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                        .expect("Scheduled punches must be HH:MM times separated by commas")
+                })
+                .collect();
+        }
+        _ => panic!(
+            "Unknown setting. Available settings: require_rollover_confirmation, stale_journey_limit_hours, enforcement_mode, policy_preset, rounding_minutes, punch_tolerance_minutes, daily_tolerance_minutes, scheduled_punches"
+        ),
+    }
+
+    clockedin_service.set_settings(settings);
+    clockedin_service.set_policy(policy);
+}
+
+fn add_policy_version(
+    clockedin_service: &mut ClockedInService,
+    date: Option<&String>,
+    preset: Option<&String>,
+) {
+    let usage =
+        "Incorrect program usage. Program usage example: ./clockin policy_from 2024-07-01 clt";
+    let effective_from = NaiveDate::parse_from_str(date.expect(usage), "%Y-%m-%d")
+        .expect("Error occurred during date parsing");
+    let mut policy = PolicyPreset::from_name(preset.expect(usage))
+        .expect("Unknown policy preset. Available presets: clt, eu_wtd, us_flsa")
+        .policy();
+    policy.day.clock_time = clockedin_service
+        .policy_schedule()
+        .policy_at(effective_from)
+        .day
+        .clock_time
+        .clone();
+
+    clockedin_service.add_policy_version(effective_from, policy);
+}
+
+fn parse_date_time(value: &str) -> DateTime<Utc> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .map(|naive| naive.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|date| date.to_utc()))
+        .expect("Error occurred during date and time parsing")
+}
+
+fn parse_minutes(value: &str) -> TimeDelta {
+    let minutes: i64 = value
+        .parse()
+        .expect("Error occurred during setting parsing");
+    TimeDelta::minutes(minutes)
+}
+
+fn print_compliance_report(clockedin_service: &ClockedInService, args: &[String]) {
+    let usage = "Incorrect program usage. Program usage example: ./clockin report 2024-07-01 2024-07-31 csv";
+    let parse_date = |value: Option<&String>| {
+        NaiveDate::parse_from_str(value.expect(usage), "%Y-%m-%d")
+            .expect("Error occurred during date parsing")
+    };
+    let report =
+        clockedin_service.compliance_report(parse_date(args.first()), parse_date(args.get(1)));
+
+    match args.get(2).map(String::as_str) {
+        None | Some("text") => print!("{}", report),
+        Some("csv") => print!("{}", report.to_csv()),
+        Some("json") => println!(
+            "{}",
+            report
+                .to_json()
+                .expect("Error occurred during report serialization")
+        ),
+        Some(_) => panic!("Unknown report format. Available formats: text, csv, json"),
+    }
+}
+
+fn justify_violation(clockedin_service: &mut ClockedInService, date: Option<&String>) {
+    let date = date
+        .map(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+        .expect("Incorrect program usage. Program usage example: ./clockin justify 2024-07-10")
+        .expect("Error occurred during date parsing");
+    let violations = clockedin_service.violations_on(date);
+    if violations.is_empty() {
+        println!("{} {}", "No violations recorded on".bright_blue(), date);
+        return;
+    }
+
+    for (index, violation) in violations.iter().enumerate() {
+        println!("{}. {}", index, violation);
+    }
+    println!("Violation to justify:");
+    let mut buffer = String::new();
+    io::stdin().read_line(&mut buffer).unwrap();
+    let Some(violation) = buffer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| violations.get(index))
+    else {
+        return;
+    };
+
+    println!("Justification:");
+    let mut justification = String::new();
+    io::stdin().read_line(&mut justification).unwrap();
+    let acknowledged = confirm("Acknowledge the violation? [y/N]");
+
+    if let Err(err) = clockedin_service.justify_violation(
+        date,
+        violation.kind,
+        justification.trim().to_string(),
+        acknowledged,
+    ) {
+        panic_epilogue(clockedin_service, err);
+    }
+}
+
+fn recover_stale_journey(clockedin_service: &mut ClockedInService, now: DateTime<Utc>) {
+    let Some(stale_journey) = clockedin_service.stale_journey(now) else {
+        return;
+    };
+
+    println!(
+        "{} {}{}",
+        "Journey started at".bright_yellow().bold(),
+        stale_journey
+            .starting_time
+            .to_string()
+            .bright_yellow()
+            .bold(),
+        " is still open. Forgot to clock out?"
+            .bright_yellow()
+            .bold()
+    );
+    println!("0. Close at the expected end of the day");
+    println!("1. Close at a given time (HH:MM)");
+    println!("2. Discard the journey");
+    println!("Any other key keeps the journey open.");
+    let mut buffer = String::new();
+    io::stdin().read_line(&mut buffer).unwrap();
+
+    let recovery = match buffer.trim() {
+        "0" => StaleJourneyRecovery::CloseAtExpectedEnd,
+        "1" => {
+            println!("Clock-out time (HH:MM):");
+            let mut time_buffer = String::new();
+            io::stdin().read_line(&mut time_buffer).unwrap();
+            let ending_time = NaiveTime::parse_from_str(time_buffer.trim(), "%H:%M")
+                .expect("Error occurred during time parsing");
+            StaleJourneyRecovery::CloseAt(DateTime::from_naive_utc_and_offset(
+                NaiveDateTime::new(stale_journey.starting_time.date_naive(), ending_time),
+                Utc,
+            ))
+        }
+        "2" => StaleJourneyRecovery::Discard,
+        _ => return,
+    };
+
+    if let Err(err) = clockedin_service.recover_stale_journey(recovery, now) {
+        panic_epilogue(clockedin_service, err);
+    }
+}
+
+/// Runs `operation` after warning about the violations `action` would create,
+/// asking for a justification when the enforcement mode requires one.
+fn perform_checked<T>(
+    clockedin_service: &mut ClockedInService,
+    action: ComplianceAction,
+    time: DateTime<Utc>,
+    operation: impl Fn(&mut ClockedInService) -> Result<T, ClockedInServiceError>,
+) -> Result<T, ClockedInServiceError> {
+    let enforcement_mode = clockedin_service.settings().enforcement_mode;
+    if enforcement_mode == EnforcementMode::Advisory
+        || (enforcement_mode == EnforcementMode::Strict && action != ComplianceAction::ClockIn)
+    {
+        display_violations("Warning: ", &clockedin_service.check_action(action, time));
+    }
+
+    match operation(clockedin_service) {
+        Err(ClockedInServiceError::JustificationRequired(violations)) => {
+            display_violations("Needs justification: ", &violations);
+            println!(
+                "{}",
+                "Justification (empty cancels):".bright_yellow().bold()
+            );
+            let mut buffer = String::new();
+            io::stdin().read_line(&mut buffer).unwrap();
+            if buffer.trim().is_empty() {
+                return Err(ClockedInServiceError::JustificationRequired(violations));
+            }
+            clockedin_service.justify_next_action(buffer.trim().to_string());
+            operation(clockedin_service)
+        }
+        Err(ClockedInServiceError::ComplianceRefused(violations)) => {
+            display_violations("Refused: ", &violations);
+            Err(ClockedInServiceError::ComplianceRefused(violations))
+        }
+        result => result,
+    }
+}
+
+fn display_violations(label: &str, violations: &[Violation]) {
+    for violation in violations {
+        println!(
+            "{}{}",
+            label.red().on_bright_white().bold(),
+            violation.to_string().red().on_bright_white().bold()
+        );
+    }
+}
+
+fn confirm(question: &str) -> bool {
+    println!("{}", question.bright_yellow().bold());
+    let mut buffer = String::new();
+    io::stdin().read_line(&mut buffer).unwrap();
+    matches!(buffer.trim(), "y" | "Y" | "yes")
+}
+
 fn display_general_information(
     clockedin_service: &ClockedInService,
     current_delta: clockedin_utils::delta_hours::DeltaHours,
@@ -155,6 +680,18 @@ fn display_general_information(
     } else {
         println!("Current Delta (until today): {}", current_delta);
     }
+    if !clockedin_service.settlements().is_empty() {
+        println!("{}", "Closed periods:".bright_blue());
+        for settlement in clockedin_service.settlements() {
+            println!("{}{}", " * ".bright_cyan(), settlement);
+        }
+    }
+    if !clockedin_service.ledger().is_empty() {
+        println!("{}", "Balance entries:".bright_blue());
+        for entry in clockedin_service.ledger() {
+            println!("{}{}", " * ".bright_cyan(), entry);
+        }
+    }
     println!("{}", "This week history:".bright_blue());
     for item in clockedin_service.worked_hours_this_week() {
         let (worked_hours_today, worked_minutes_today) = time_delta_into_hour_minute(&item.1);
@@ -184,54 +721,171 @@ fn display_general_information(
     } else {
         println!("{}", "Finished work day".bright_blue().bold(),);
     }
-    if let Some((normal_recommendation, another_journey_is_needed)) =
-        clockedin_service.recommended_journey(TimeDelta::hours(6))
-    {
+    let leave_recommendations = match clockedin_service.leave_recommendations() {
+        Ok(recommendations) => recommendations,
+        Err(err) => panic_epilogue(clockedin_service, err),
+    };
+    for recommendation in leave_recommendations {
+        let label = match recommendation.target {
+            LeaveTarget::Today => "Leave to keep today at zero:",
+            LeaveTarget::ThisWeek => "Leave to zero this week's delta:",
+            LeaveTarget::Balance => "Leave to zero the full balance:",
+        };
         print!(
             "{} {}{}",
-            "Recommended ending of 6 hours day of work:".bright_blue(),
-            normal_recommendation.to_string().bright_blue().bold(),
+            label.bright_blue(),
+            recommendation.leave_at.to_string().green().bold(),
             ".".bright_blue()
         );
-        if another_journey_is_needed {
-            println!("{}", " Another work journey is needed.".bright_red().bold())
-        } else {
-            println!()
+        match recommendation.limit {
+            Some(limit) => {
+                let reason = match limit {
+                    LeaveLimit::MaxJourneyHours => "maximum journey length",
+                    LeaveLimit::MaxDailyHours => "maximum daily hours",
+                    LeaveLimit::RequiredBreak => "required break",
+                };
+                println!(
+                    "{}{}{}",
+                    " Limited by the ".bright_red().bold(),
+                    reason.bright_red().bold(),
+                    ".".bright_red().bold()
+                );
+            }
+            None => println!(),
         }
     }
-    if let Some((normal_recommendation, another_journey_is_needed)) =
-        clockedin_service.recommended_journey(EXPECTED_WORK_JOURNEY_TIME_DELTA)
-    {
+    if let Some((average_weekly_hours, averaged_weeks)) = clockedin_service.average_weekly_hours() {
+        let week_policy = &clockedin_service.policy().week;
+        let (average_hours, average_minutes) = time_delta_into_hour_minute(&average_weekly_hours);
+        let reference_period = if averaged_weeks < week_policy.reference_period_weeks {
+            format!(
+                "{} of {} finished weeks: ",
+                averaged_weeks, week_policy.reference_period_weeks
+            )
+        } else {
+            format!("{} finished weeks: ", averaged_weeks)
+        };
         print!(
-            "{} {}{}",
-            "Recommended ending of 8 hours day of work:".bright_blue(),
-            normal_recommendation.to_string().green().bold(),
-            ".".bright_blue()
+            "{} {}{}{}{}",
+            "Average weekly hours over the last".bright_blue(),
+            reference_period.bright_blue(),
+            average_hours.to_string().bright_blue().bold(),
+            "h:".bright_blue(),
+            average_minutes.to_string().bright_blue().bold(),
         );
-        if another_journey_is_needed {
-            println!("{}", " Another work journey is needed.".bright_red().bold())
+        if week_policy.max_average_weekly_hours > TimeDelta::zero() {
+            println!(
+                "{}{}{}",
+                "m (limit ".bright_blue(),
+                week_policy
+                    .max_average_weekly_hours
+                    .num_hours()
+                    .to_string()
+                    .bright_blue()
+                    .bold(),
+                "h).".bright_blue()
+            );
         } else {
-            println!()
+            println!("{}", "m.".bright_blue());
         }
     }
-    if let Some((normal_recommendation, another_journey_is_needed)) = clockedin_service
-        .recommended_journey(
-            EXPECTED_WORK_JOURNEY_TIME_DELTA + EXPECTED_OVERTIME_WORK_JOURNEY_TIME_DELTA,
-        )
-    {
-        print!(
+    if let Some(earliest_clock_in) = clockedin_service.earliest_clock_in() {
+        println!(
             "{} {}{}",
-            "Recommended ending of 10 hours day of work:".bright_blue(),
-            normal_recommendation.to_string().red().bold(),
-            ".".green()
+            "Earliest clock-in respecting the inter-day rest:".bright_blue(),
+            earliest_clock_in.to_string().bright_blue().bold(),
+            ".".bright_blue()
         );
-        if another_journey_is_needed {
-            println!("{}", " Another work journey is needed.".bright_red().bold())
-        } else {
-            println!()
+    }
+    match clockedin_service.day_plan() {
+        Ok(Some(day_plan)) if day_plan.needs_another_journey() => {
+            println!(
+                "{}",
+                "Another work journey is needed. Day plan:".bright_blue()
+            );
+            println!(
+                "{}{}",
+                " * Stop the current journey by ".bright_cyan(),
+                day_plan.stop_by.to_string().bright_blue().bold()
+            );
+            if let Some(return_at) = day_plan.return_at {
+                println!(
+                    "{}{}",
+                    " * Return no earlier than ".bright_cyan(),
+                    return_at.to_string().bright_blue().bold()
+                );
+            }
+            println!(
+                "{}{}",
+                " * Clock out at ".bright_cyan(),
+                day_plan.clock_out.to_string().green().bold()
+            );
+            for violation in day_plan.violations {
+                println!(
+                    "{}{}",
+                    "Planned day: ".red().on_bright_white().bold(),
+                    violation.to_string().red().on_bright_white().bold()
+                );
+            }
         }
+        Ok(_) => (),
+        Err(err) => println!(
+            "{}{}",
+            "Day plan unavailable: ".bright_yellow(),
+            err.to_string().bright_yellow()
+        ),
+    }
+    if let Some(violation) = clockedin_service.current_journey_violation(clockedin_service.now()) {
+        println!(
+            "{}{}",
+            "Current journey: ".red().on_bright_white().bold(),
+            violation.to_string().red().on_bright_white().bold()
+        );
+    }
+    let today = clockedin_service.now().date_naive();
+    let week_start = today - TimeDelta::days(today.weekday().num_days_from_monday().into());
+    display_violations(
+        "Last week: ",
+        &clockedin_service
+            .compliance_report(
+                week_start - TimeDelta::weeks(1),
+                week_start - TimeDelta::days(1),
+            )
+            .violations,
+    );
+    display_violations(
+        "This week: ",
+        &clockedin_service
+            .compliance_report(week_start, today)
+            .violations,
+    );
+}
+
+fn display_premium_information(clockedin_service: &ClockedInService, today: NaiveDate) {
+    let premium_policy = &clockedin_service.policy().premium;
+    let premiums = [
+        ("Today", clockedin_service.premium_buckets_today()),
+        ("This week", clockedin_service.premium_buckets_this_week()),
+        (
+            "This month",
+            clockedin_service.premium_buckets_for_month(today.year(), today.month()),
+        ),
+    ];
+
+    println!("{}", "Premium hours:".bright_blue());
+    for (label, buckets) in premiums {
+        println!(
+            "{}{}{}{}{}{}",
+            " * ".bright_cyan(),
+            label.bright_blue(),
+            " -> ".bright_blue(),
+            buckets.to_string().bright_blue(),
+            " | weighted hours: ".bright_blue(),
+            format!("{:.2}", buckets.weighted_hours(premium_policy))
+                .bright_blue()
+                .bold()
+        );
     }
-    clockedin_service.display_last_violations();
 }
 
 fn time_delta_into_hour_minute(worked_hours_today_time_delta: &chrono::TimeDelta) -> (i64, i64) {
@@ -241,7 +895,7 @@ fn time_delta_into_hour_minute(worked_hours_today_time_delta: &chrono::TimeDelta
     (worked_hours_today, worked_minutes_today)
 }
 
-fn panic_epilogue(clockedin_service: &ClockedInService, err: ClockedInServiceError) {
+fn panic_epilogue(clockedin_service: &ClockedInService, err: ClockedInServiceError) -> ! {
     epilogue(clockedin_service);
     panic!("Error occurred: {}", err)
 }
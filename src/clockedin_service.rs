@@ -4,22 +4,33 @@ use std::{
 };
 
 use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
-use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 const LONG_TERM_REGISTRY_STATE_FILE_NAME: &str = "long_term_registry_state.json";
 pub const EXPECTED_WORK_JOURNEY_TIME_DELTA: TimeDelta = TimeDelta::hours(8);
 pub const EXPECTED_OVERTIME_WORK_JOURNEY_TIME_DELTA: TimeDelta = TimeDelta::hours(2);
-
-use crate::work_days::MAX_HOURS_PER_JOURNEY;
+pub const STALE_JOURNEY_LIMIT_TIME_DELTA: TimeDelta = TimeDelta::hours(16);
 
 use super::{
+    clock::{Clock, SystemClock},
+    compliance_report::ComplianceReport,
     delta_hours::DeltaHours,
+    integrity::{
+        check_current_day, check_weeks, repair_weeks, IntegrityIssue, IntegrityProblem,
+        RegistryLocation,
+    },
+    ledger::{LedgerEntry, LedgerEntryKind, PeriodSettlement},
     long_term_registry::{LongTermRegistry, LongTermRegistryError},
-    work_days::WorkDay,
+    policy::{PolicySchedule, WorkPolicy},
+    premium::PremiumBuckets,
+    punch_pairing::{PairingProposal, PunchPairingError, DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA},
+    recommendations::{DayPlan, LeaveRecommendation, LeaveTarget},
+    recompute::Recomputation,
+    violations::{Violation, ViolationKind, ViolationMeasure},
+    work_days::{journey_length_violation, WorkDay, WorkDayError},
     work_journey::{IncompleteWorkJourney, WorkJourney, WorkJourneyError},
-    work_week::WorkWeek,
+    work_week::{WorkWeek, MAX_DAYS_PER_WEEK},
 };
 
 #[derive(Error, Debug)]
@@ -28,6 +39,8 @@ pub enum ClockedInServiceError {
     WorkJourneyAlreadyInProgess(DateTime<Utc>),
     #[error("Error during work journey ending: {0}.")]
     WorkJourneyEndingError(WorkJourneyError),
+    #[error("Error during work day ending: {0}")]
+    WorkDayError(WorkDayError),
     #[error("No work journey in progress.")]
     NoneCurrentWorkJourney(),
     #[error("Error during long term registry acess {0}")]
@@ -40,6 +53,105 @@ pub enum ClockedInServiceError {
     ClockInDaySameAsFinishedWeekInRegistry,
     #[error("ClockIn day in the last day of the current work week.")]
     ClockInDaySameAsLastFinishedWorkDay,
+    #[error("Work from {0} is still open and must be closed before clocking in.")]
+    RolloverConfirmationRequired(NaiveDate),
+    #[error("Punches can only be imported when there is no pending day or week.")]
+    PendingWorkBeforeImport,
+    #[error("Error during punch import: {0}")]
+    PunchImportError(PunchPairingError),
+    #[error("The action would create {} violation(s) and needs a justification.", .0.len())]
+    JustificationRequired(Vec<Violation>),
+    #[error("The action was refused, it would create {} violation(s).", .0.len())]
+    ComplianceRefused(Vec<Violation>),
+    #[error("No {1:?} violation found on {0}.")]
+    ViolationNotFound(NaiveDate, ViolationKind),
+    #[error("A period can't be closed on {0}, after today.")]
+    PeriodClosedInTheFuture(NaiveDate),
+    #[error("Work from {0} is still open and must be finished before closing the period.")]
+    PeriodHasOpenWork(NaiveDate),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceSettings {
+    /// Ask before a clock-in on a new date closes the pending day or week.
+    pub require_rollover_confirmation: bool,
+    /// Open journeys older than this are considered a forgotten clock-out.
+    #[serde(with = "crate::policy::seconds")]
+    pub stale_journey_limit: TimeDelta,
+    pub enforcement_mode: EnforcementMode,
+}
+
+impl Default for ServiceSettings {
+    fn default() -> Self {
+        Self {
+            require_rollover_confirmation: false,
+            stale_journey_limit: STALE_JOURNEY_LIMIT_TIME_DELTA,
+            enforcement_mode: EnforcementMode::Advisory,
+        }
+    }
+}
+
+/// What happens when an action would create a violation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum EnforcementMode {
+    /// The action goes through, the violations are only reported.
+    #[default]
+    Advisory,
+    /// The action needs a justification to go through.
+    Confirm,
+    /// Clock-ins are refused, clock-outs go through and are only reported.
+    Strict,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ComplianceAction {
+    ClockIn,
+    ClockOut,
+    EndDay,
+}
+
+/// Action performed despite its violations, with the reason given for it.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ComplianceOverride {
+    pub performed_at: DateTime<Utc>,
+    pub action: ComplianceAction,
+    pub violations: Vec<Violation>,
+    pub justification: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PunchOutcome {
+    ClockedIn,
+    ClockedOut,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StaleJourney {
+    pub starting_time: DateTime<Utc>,
+    pub elapsed: TimeDelta,
+    pub crosses_day_cutover: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StaleJourneyRecovery {
+    CloseAtExpectedEnd,
+    CloseAt(DateTime<Utc>),
+    Discard,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CorrectionAction {
+    ClosedAtExpectedEnd(DateTime<Utc>),
+    ClosedAt(DateTime<Utc>),
+    Discarded,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct JourneyCorrection {
+    pub recorded_at: DateTime<Utc>,
+    pub journey_start: DateTime<Utc>,
+    pub action: CorrectionAction,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,6 +160,18 @@ pub struct ClockedInService {
     current_work_journey: Option<IncompleteWorkJourney>,
     current_work_day: Vec<WorkJourney>,
     current_work_week: Option<WorkWeek>,
+    #[serde(flatten)]
+    policy: PolicySchedule,
+    #[serde(default)]
+    settings: ServiceSettings,
+    #[serde(default)]
+    corrections: Vec<JourneyCorrection>,
+    #[serde(default)]
+    compliance_overrides: Vec<ComplianceOverride>,
+    #[serde(skip)]
+    pending_justification: Option<String>,
+    #[serde(skip, default = "default_clock")]
+    clock: Box<dyn Clock>,
 }
 
 impl ClockedInService {
@@ -56,16 +180,64 @@ impl ClockedInService {
         let current_work_journey = None;
         let current_work_day = Vec::new();
         let current_work_week = None;
+        let policy = PolicySchedule::default();
+        let settings = ServiceSettings::default();
 
         ClockedInService {
             long_term_registry,
             current_work_journey,
             current_work_day,
             current_work_week,
+            policy,
+            settings,
+            corrections: Vec::new(),
+            compliance_overrides: Vec::new(),
+            pending_justification: None,
+            clock: default_clock(),
         }
     }
 
+    /// Policy in force today.
+    pub fn policy(&self) -> &WorkPolicy {
+        self.policy.policy_at(self.now().date_naive())
+    }
+
+    /// Puts `policy` in force from today on. Past days keep the rules they
+    /// were recorded under.
+    pub fn set_policy(&mut self, policy: WorkPolicy) {
+        let today = self.now().date_naive();
+        self.policy.add_version(today, policy);
+    }
+
+    pub fn policy_schedule(&self) -> &PolicySchedule {
+        &self.policy
+    }
+
+    /// Puts `policy` in force from `effective_from` on. Days already recorded
+    /// keep the expected hours they were evaluated with.
+    pub fn add_policy_version(&mut self, effective_from: NaiveDate, policy: WorkPolicy) {
+        self.policy.add_version(effective_from, policy);
+    }
+
+    pub fn settings(&self) -> &ServiceSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: ServiceSettings) {
+        self.settings = settings;
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
     pub fn clock_in(&mut self, starting_time: DateTime<Utc>) -> Result<(), ClockedInServiceError> {
+        self.check_period_is_open(starting_time.date_naive())?;
+
         if let Some(last_clock_out) = self.long_term_registry.last_clock_out_last_week() {
             if same_work_day(starting_time, last_clock_out) {
                 return Err(ClockedInServiceError::ClockInDaySameAsFinishedWeekInRegistry);
@@ -80,21 +252,37 @@ impl ClockedInService {
             }
         }
 
-        match &self.current_work_journey {
-            Some(initiated_work_journey) => {
-                Err(ClockedInServiceError::WorkJourneyAlreadyInProgess(
-                    initiated_work_journey.starting_time,
-                ))
-            }
-            None => {
-                let new_work_journey = IncompleteWorkJourney::new(starting_time);
-                self.current_work_journey = Some(new_work_journey);
-                Ok(())
+        if let Some(initiated_work_journey) = &self.current_work_journey {
+            return Err(ClockedInServiceError::WorkJourneyAlreadyInProgess(
+                initiated_work_journey.starting_time,
+            ));
+        }
+
+        let compliance_override = self.enforce(ComplianceAction::ClockIn, starting_time)?;
+
+        if let Some(pending_date) = self.pending_rollover(starting_time) {
+            if self.settings.require_rollover_confirmation {
+                return Err(ClockedInServiceError::RolloverConfirmationRequired(
+                    pending_date,
+                ));
             }
+            self.roll_over_pending_work(starting_time)?;
         }
+
+        let new_work_journey = IncompleteWorkJourney::new(starting_time);
+        self.current_work_journey = Some(new_work_journey);
+        self.record_override(compliance_override);
+        Ok(())
     }
 
     pub fn clock_out(&mut self, ending_time: DateTime<Utc>) -> Result<(), ClockedInServiceError> {
+        let compliance_override = self.enforce(ComplianceAction::ClockOut, ending_time)?;
+        self.close_journey(ending_time)?;
+        self.record_override(compliance_override);
+        Ok(())
+    }
+
+    fn close_journey(&mut self, ending_time: DateTime<Utc>) -> Result<(), ClockedInServiceError> {
         match &mut self.current_work_journey {
             Some(current_work_journey) => {
                 let finished_journey = current_work_journey
@@ -110,32 +298,351 @@ impl ClockedInService {
         }
     }
 
+    pub fn is_clocked_in(&self) -> bool {
+        self.current_work_journey.is_some()
+    }
+
+    /// Toggles between clock-in and clock-out like a physical time clock. Days
+    /// and weeks are closed by the date rules of `clock_in`.
+    pub fn punch(
+        &mut self,
+        punch_time: DateTime<Utc>,
+    ) -> Result<PunchOutcome, ClockedInServiceError> {
+        if self.current_work_journey.is_some() {
+            self.clock_out(punch_time)?;
+            Ok(PunchOutcome::ClockedOut)
+        } else {
+            self.clock_in(punch_time)?;
+            Ok(PunchOutcome::ClockedIn)
+        }
+    }
+
     pub fn clock_out_and_end_work_day(
         &mut self,
         ending_time: DateTime<Utc>,
     ) -> Result<(), ClockedInServiceError> {
-        self.clock_out(ending_time)?;
+        let starting_time = self
+            .current_work_journey
+            .as_ref()
+            .ok_or(ClockedInServiceError::NoneCurrentWorkJourney())?
+            .starting_time;
+        let finished_journey = WorkJourney::new(starting_time, ending_time)
+            .map_err(ClockedInServiceError::WorkJourneyEndingError)?;
+        let mut journeys = self.current_work_day.clone();
+        journeys.push(finished_journey);
+        WorkDay::validate(&journeys).map_err(ClockedInServiceError::WorkDayError)?;
+        self.check_period_is_open(starting_time.date_naive())?;
 
-        let finished_work_day = WorkDay::new(&self.current_work_day);
-        self.current_work_day = Vec::new();
+        let compliance_override = self.enforce(ComplianceAction::EndDay, ending_time)?;
+        self.close_journey(ending_time)?;
+        self.record_override(compliance_override);
+        self.end_work_day()
+    }
 
-        match &mut self.current_work_week {
-            Some(current_work_week) => current_work_week.append_day(&finished_work_day),
-            None => {
-                let mut new_work_week = WorkWeek::new();
-                new_work_week.append_day(&finished_work_day);
-                self.current_work_week = Some(new_work_week);
+    pub fn clock_out_and_end_work_week(
+        &mut self,
+        ending_time: DateTime<Utc>,
+    ) -> Result<(), ClockedInServiceError> {
+        self.clock_out_and_end_work_day(ending_time)?;
+        self.end_work_week();
+
+        Ok(())
+    }
+
+    /// Open journey left running for longer than the configured limit or
+    /// started on a previous date.
+    pub fn stale_journey(&self, now: DateTime<Utc>) -> Option<StaleJourney> {
+        let current_journey = self.current_work_journey.as_ref()?;
+        let elapsed = current_journey.elapsed(now);
+        let crosses_day_cutover = current_journey.starting_time.date_naive() != now.date_naive();
+
+        if elapsed > self.settings.stale_journey_limit || crosses_day_cutover {
+            Some(StaleJourney {
+                starting_time: current_journey.starting_time,
+                elapsed,
+                crosses_day_cutover,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn recover_stale_journey(
+        &mut self,
+        recovery: StaleJourneyRecovery,
+        now: DateTime<Utc>,
+    ) -> Result<(), ClockedInServiceError> {
+        let journey_start = self
+            .current_work_journey
+            .as_ref()
+            .ok_or(ClockedInServiceError::NoneCurrentWorkJourney())?
+            .starting_time;
+
+        let action = match recovery {
+            StaleJourneyRecovery::CloseAtExpectedEnd => {
+                let day_policy = &self.policy.policy_at(journey_start.date_naive()).day;
+                let remaining_hours =
+                    (day_policy.expected_hours - self.worked_hours_today()).max(TimeDelta::zero());
+                let expected_end = journey_start + remaining_hours;
+                self.close_journey(expected_end)?;
+                CorrectionAction::ClosedAtExpectedEnd(expected_end)
+            }
+            StaleJourneyRecovery::CloseAt(ending_time) => {
+                self.close_journey(ending_time)?;
+                CorrectionAction::ClosedAt(ending_time)
+            }
+            StaleJourneyRecovery::Discard => {
+                self.current_work_journey = None;
+                CorrectionAction::Discarded
+            }
+        };
+
+        if action != CorrectionAction::Discarded {
+            if let Some(closed_journey) = self.current_work_day.last_mut() {
+                closed_journey.mark_corrected();
             }
         }
+
+        self.corrections.push(JourneyCorrection {
+            recorded_at: now,
+            journey_start,
+            action,
+        });
         Ok(())
     }
 
-    pub fn clock_out_and_end_work_week(
+    pub fn corrections(&self) -> &[JourneyCorrection] {
+        &self.corrections
+    }
+
+    /// Violations that performing `action` at `time` would create. A clock-out
+    /// that doesn't end the day is only checked against the rules that are
+    /// final at that moment. Invalid actions report nothing here and fail when
+    /// performed.
+    pub fn check_action(&self, action: ComplianceAction, time: DateTime<Utc>) -> Vec<Violation> {
+        match action {
+            ComplianceAction::ClockIn => self.check_clock_in(time),
+            ComplianceAction::ClockOut | ComplianceAction::EndDay => {
+                let Some(current_journey) = &self.current_work_journey else {
+                    return Vec::new();
+                };
+                let Ok(journey) = WorkJourney::new(current_journey.starting_time, time) else {
+                    return Vec::new();
+                };
+                let mut journeys = self.current_work_day.clone();
+                journeys.push(journey);
+
+                WorkDay::new(&journeys, &self.policy.policy_at(time.date_naive()).day)
+                    .map(|work_day| work_day.get_violations())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|violation| {
+                        action == ComplianceAction::EndDay
+                            || matches!(
+                                violation.kind,
+                                ViolationKind::ExceededMaxJourneyLength
+                                    | ViolationKind::ExceededMaxHours
+                            )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Justification used by the next action that needs one in
+    /// `EnforcementMode::Confirm`.
+    pub fn justify_next_action(&mut self, justification: String) {
+        self.pending_justification = Some(justification);
+    }
+
+    pub fn compliance_overrides(&self) -> &[ComplianceOverride] {
+        &self.compliance_overrides
+    }
+
+    fn check_clock_in(&self, starting_time: DateTime<Utc>) -> Vec<Violation> {
+        let starting_date = starting_time.date_naive();
+        let policy = self.policy.policy_at(starting_date);
+        let mut violations = Vec::new();
+
+        if let Some(last_clock_out) = self.last_clock_out() {
+            let rest = starting_time - last_clock_out;
+            if last_clock_out.date_naive() != starting_date && rest < policy.week.min_inter_day_rest
+            {
+                violations.push(
+                    Violation::new(
+                        ViolationKind::InterDayRest,
+                        starting_date,
+                        ViolationMeasure::Seconds(rest.num_seconds()),
+                        ViolationMeasure::Seconds(policy.week.min_inter_day_rest.num_seconds()),
+                    )
+                    .with_days(vec![last_clock_out.date_naive(), starting_date]),
+                );
+            }
+        }
+
+        let journeys_today = self
+            .current_work_day
+            .iter()
+            .filter(|journey| journey.get_starting_time().date_naive() == starting_date)
+            .count();
+        if journeys_today >= policy.day.max_journeys {
+            violations.push(
+                Violation::new(
+                    ViolationKind::ExceededMaxJourneys,
+                    starting_date,
+                    ViolationMeasure::Count(journeys_today + 1),
+                    ViolationMeasure::Count(policy.day.max_journeys),
+                )
+                .with_journeys(vec![journeys_today]),
+            );
+        }
+
+        violations
+    }
+
+    /// Checks `action` against the enforcement mode, returning the override to
+    /// record once the action succeeded. Strict mode never refuses a
+    /// clock-out, it only reports.
+    fn enforce(
+        &self,
+        action: ComplianceAction,
+        time: DateTime<Utc>,
+    ) -> Result<Option<ComplianceOverride>, ClockedInServiceError> {
+        let violations = self.check_action(action, time);
+        if violations.is_empty() {
+            return Ok(None);
+        }
+
+        match (self.settings.enforcement_mode, &self.pending_justification) {
+            (EnforcementMode::Advisory, _) => Ok(None),
+            (EnforcementMode::Confirm, Some(justification)) => Ok(Some(ComplianceOverride {
+                performed_at: time,
+                action,
+                violations,
+                justification: justification.clone(),
+            })),
+            (EnforcementMode::Confirm, None) => {
+                Err(ClockedInServiceError::JustificationRequired(violations))
+            }
+            (EnforcementMode::Strict, _) if action != ComplianceAction::ClockIn => Ok(None),
+            (EnforcementMode::Strict, _) => {
+                Err(ClockedInServiceError::ComplianceRefused(violations))
+            }
+        }
+    }
+
+    fn record_override(&mut self, compliance_override: Option<ComplianceOverride>) {
+        if let Some(compliance_override) = compliance_override {
+            self.pending_justification = None;
+            self.compliance_overrides.push(compliance_override);
+        }
+    }
+
+    fn last_clock_out(&self) -> Option<DateTime<Utc>> {
+        self.current_work_day
+            .last()
+            .map(WorkJourney::get_ending_time)
+            .or_else(|| {
+                self.current_work_week
+                    .as_ref()
+                    .and_then(WorkWeek::last_clock_out_last_day_in_week)
+            })
+            .or_else(|| self.long_term_registry.last_clock_out_last_week())
+    }
+
+    /// Date of the pending day or week that a clock-in at `starting_time`
+    /// would have to close first, if any.
+    pub fn pending_rollover(&self, starting_time: DateTime<Utc>) -> Option<NaiveDate> {
+        let starting_date = starting_time.date_naive();
+
+        if let Some(pending_journey) = self.current_work_day.first() {
+            let pending_date = pending_journey.get_starting_time().date_naive();
+            if pending_date != starting_date {
+                return Some(pending_date);
+            }
+        }
+
+        self.current_work_week
+            .as_ref()
+            .and_then(WorkWeek::last_date)
+            .filter(|last_date| last_date.iso_week() != starting_date.iso_week())
+    }
+
+    /// Finalizes the pending day when `current_time` is on another date, and the
+    /// current week when `current_time` is on another ISO week.
+    pub fn roll_over_pending_work(
         &mut self,
-        ending_time: DateTime<Utc>,
+        current_time: DateTime<Utc>,
     ) -> Result<(), ClockedInServiceError> {
-        self.clock_out_and_end_work_day(ending_time)?;
+        let current_date = current_time.date_naive();
 
+        if let Some(pending_journey) = self.current_work_day.first() {
+            if pending_journey.get_starting_time().date_naive() != current_date {
+                self.end_work_day()?;
+            }
+        }
+
+        let last_week_date = self
+            .current_work_week
+            .as_ref()
+            .and_then(WorkWeek::last_date);
+        if let Some(last_week_date) = last_week_date {
+            if last_week_date.iso_week() != current_date.iso_week() {
+                self.end_work_week();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn end_work_day(&mut self) -> Result<(), ClockedInServiceError> {
+        let finished_date = self
+            .current_work_day
+            .first()
+            .ok_or(ClockedInServiceError::WorkDayError(WorkDayError::EmptyDay))?
+            .get_starting_time()
+            .date_naive();
+        self.check_period_is_open(finished_date)?;
+        let finished_work_day = WorkDay::new(
+            &self.current_work_day,
+            &self.policy.policy_at(finished_date).day,
+        )
+        .map_err(ClockedInServiceError::WorkDayError)?;
+        self.current_work_day = Vec::new();
+
+        let starts_new_week = self.current_work_week.as_ref().is_some_and(|week| {
+            week.workdays.len() >= MAX_DAYS_PER_WEEK
+                || week
+                    .last_date()
+                    .is_some_and(|last_date| last_date.iso_week() != finished_date.iso_week())
+        });
+        if starts_new_week {
+            self.end_work_week();
+        }
+
+        let policy = self.policy.policy_at(finished_date);
+        let current_work_week = self.current_work_week.get_or_insert_with(WorkWeek::new);
+        current_work_week.append_day(&finished_work_day, &policy.week);
+        current_work_week.evaluate_weekly_rules(&self.long_term_registry.history, &policy.week);
+
+        for compliance_override in &self.compliance_overrides {
+            for violation in compliance_override
+                .violations
+                .iter()
+                .filter(|violation| violation.date == finished_date)
+            {
+                current_work_week.justify_violation(
+                    violation.date,
+                    violation.kind,
+                    &compliance_override.justification,
+                    false,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn end_work_week(&mut self) {
         if let Some(current_work_week) = &self.current_work_week {
             self.long_term_registry
                 .history
@@ -144,8 +651,159 @@ impl ClockedInService {
             let new_work_week = WorkWeek::new();
             self.current_work_week = Some(new_work_week);
         }
+    }
 
-        Ok(())
+    pub fn propose_punch_import(&self, punches: &[DateTime<Utc>]) -> PairingProposal {
+        PairingProposal::from_punches(punches, DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA, &self.policy)
+    }
+
+    /// Commits a reviewed punch import into the registry, returning the dates
+    /// left out because they still need review.
+    pub fn commit_punch_import(
+        &mut self,
+        proposal: &PairingProposal,
+    ) -> Result<Vec<NaiveDate>, ClockedInServiceError> {
+        let has_pending_week = self
+            .current_work_week
+            .as_ref()
+            .is_some_and(|week| !week.workdays.is_empty());
+        if has_pending_week
+            || !self.current_work_day.is_empty()
+            || self.current_work_journey.is_some()
+        {
+            return Err(ClockedInServiceError::PendingWorkBeforeImport);
+        }
+
+        proposal
+            .commit_into(&mut self.long_term_registry, &self.policy)
+            .map_err(ClockedInServiceError::PunchImportError)
+    }
+
+    /// Records the balance carried over from a previous system, replacing the
+    /// previous opening balance.
+    pub fn set_opening_balance(
+        &mut self,
+        date: NaiveDate,
+        credit: TimeDelta,
+        note: String,
+    ) -> Result<(), ClockedInServiceError> {
+        self.long_term_registry
+            .add_ledger_entry(LedgerEntry::new(
+                date,
+                LedgerEntryKind::OpeningBalance,
+                credit,
+                note,
+            ))
+            .map_err(ClockedInServiceError::LongTermRegistryError)
+    }
+
+    pub fn add_balance_adjustment(
+        &mut self,
+        date: NaiveDate,
+        credit: TimeDelta,
+        note: String,
+    ) -> Result<(), ClockedInServiceError> {
+        self.long_term_registry
+            .add_ledger_entry(LedgerEntry::new(
+                date,
+                LedgerEntryKind::Adjustment,
+                credit,
+                note,
+            ))
+            .map_err(ClockedInServiceError::LongTermRegistryError)
+    }
+
+    pub fn ledger(&self) -> &[LedgerEntry] {
+        self.long_term_registry.get_ledger()
+    }
+
+    /// Closes the period ending on `closed_on`, counting the finished days of
+    /// the current week, and resets the balance.
+    pub fn close_period(
+        &mut self,
+        closed_on: NaiveDate,
+        note: String,
+    ) -> Result<PeriodSettlement, ClockedInServiceError> {
+        if closed_on > self.now().date_naive() {
+            return Err(ClockedInServiceError::PeriodClosedInTheFuture(closed_on));
+        }
+        let open_work_date = self
+            .current_work_day
+            .first()
+            .map(WorkJourney::get_starting_time)
+            .or(self
+                .current_work_journey
+                .as_ref()
+                .map(|journey| journey.starting_time))
+            .map(|starting_time| starting_time.date_naive());
+        if let Some(open_work_date) = open_work_date.filter(|date| *date <= closed_on) {
+            return Err(ClockedInServiceError::PeriodHasOpenWork(open_work_date));
+        }
+        self.long_term_registry
+            .close_period(closed_on, note, self.current_work_week.as_slice())
+            .map_err(ClockedInServiceError::LongTermRegistryError)
+    }
+
+    /// Refuses work dated in a closed period.
+    fn check_period_is_open(&self, date: NaiveDate) -> Result<(), ClockedInServiceError> {
+        match self.long_term_registry.closed_until() {
+            Some(closed_on) if date <= closed_on => {
+                Err(ClockedInServiceError::LongTermRegistryError(
+                    LongTermRegistryError::PeriodAlreadyClosed(closed_on),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn settlements(&self) -> &[PeriodSettlement] {
+        self.long_term_registry.get_settlements()
+    }
+
+    /// Problems found in the recorded state, with their locations.
+    pub fn check_integrity(&self) -> Vec<IntegrityIssue> {
+        let weeks = self
+            .long_term_registry
+            .history
+            .iter()
+            .chain(self.current_work_week.as_ref());
+        let mut issues = check_weeks(weeks, self.long_term_registry.closed_until());
+        issues.extend(check_current_day(&self.current_work_day));
+
+        if let Some(stale_journey) = self.stale_journey(self.now()) {
+            issues.push(IntegrityIssue {
+                location: RegistryLocation::OpenJourney,
+                problem: IntegrityProblem::StaleOpenJourney(stale_journey),
+                in_closed_period: false,
+            });
+        }
+        issues
+    }
+
+    /// Repairs the safe integrity problems outside closed periods, returning
+    /// how many were fixed.
+    pub fn repair_integrity(&mut self) -> usize {
+        let closed_until = self.long_term_registry.closed_until();
+        let current_week = self.current_work_week.as_mut().map(std::slice::from_mut);
+        repair_weeks(&mut self.long_term_registry.history, closed_until)
+            + current_week.map_or(0, |week| repair_weeks(week, closed_until))
+    }
+
+    /// Rebuilds the recorded weeks, including the current one, from their raw
+    /// journeys under the policy schedule, without changing the registry.
+    pub fn propose_recompute(&self) -> Recomputation {
+        let mut weeks = self.long_term_registry.history.clone();
+        weeks.extend(self.current_work_week.clone());
+        Recomputation::new(&weeks, &self.policy, self.long_term_registry.closed_until())
+    }
+
+    /// Replaces the recorded weeks with a reviewed recomputation.
+    pub fn commit_recompute(&mut self, recomputation: Recomputation) {
+        let mut weeks = recomputation.weeks;
+        if self.current_work_week.is_some() {
+            self.current_work_week = weeks.pop();
+        }
+        self.long_term_registry.history = weeks;
     }
 
     pub fn worked_delta_until_today(&self) -> Result<DeltaHours, ClockedInServiceError> {
@@ -164,6 +822,18 @@ impl ClockedInService {
         Ok(long_time_registry_delta)
     }
 
+    /// Average weekly hours over the finished weeks of the policy's reference
+    /// period, with the number of weeks averaged, which is lower than the
+    /// period while the history is shorter.
+    pub fn average_weekly_hours(&self) -> Option<(TimeDelta, usize)> {
+        let reference_period_weeks = self.policy().week.reference_period_weeks;
+        let averaged_weeks = reference_period_weeks.min(self.long_term_registry.history.len());
+
+        self.long_term_registry
+            .average_weekly_hours(reference_period_weeks)
+            .map(|average| (TimeDelta::seconds(average), averaged_weeks))
+    }
+
     pub fn worked_hours_today(&self) -> TimeDelta {
         self.current_work_day
             .iter()
@@ -175,155 +845,215 @@ impl ClockedInService {
 
         if let Some(week) = &self.current_work_week {
             for day in &week.workdays {
-                return_vec.push((
-                    day.first_clock_in().date_naive(),
-                    TimeDelta::seconds(day.worked_hours()),
-                ))
+                if let Some(date) = day.date() {
+                    return_vec.push((date, TimeDelta::seconds(day.worked_hours())))
+                }
             }
         }
 
         return_vec
     }
 
-    pub fn recommended_journey(
-        &self,
-        expected_work_journey: TimeDelta,
-    ) -> Option<(DateTime<Utc>, bool)> {
-        let worked_hours_today = self.worked_hours_today();
-        let remaining_hours = expected_work_journey - worked_hours_today;
-
-        if remaining_hours < TimeDelta::zero() && self.current_work_journey.is_some() {
-            return Some((chrono::Utc::now() - chrono::TimeDelta::hours(3), false));
-        }
-
-        if let Some(current_journey) = &self.current_work_journey {
-            if remaining_hours > MAX_HOURS_PER_JOURNEY {
-                let current_journey_start = current_journey.starting_time;
-                let preview_journey_end = current_journey_start + TimeDelta::hours(6);
-                Some((preview_journey_end, true))
-            } else {
-                let current_journey_start = current_journey.starting_time;
-                let preview_journey_end = current_journey_start + remaining_hours;
-                Some((preview_journey_end, false))
+    pub fn premium_buckets_today(&self) -> PremiumBuckets {
+        let policy = self.policy();
+        PremiumBuckets::from_journeys(
+            &self.current_work_day,
+            policy.daily_overtime_threshold(),
+            &policy.premium,
+        )
+    }
+
+    /// Buckets of the finished days of the week and of today's journeys, with
+    /// the weekly overtime threshold applied to their sum.
+    pub fn premium_buckets_this_week(&self) -> PremiumBuckets {
+        let mut buckets = PremiumBuckets::default();
+
+        if let Some(week) = &self.current_work_week {
+            for day in &week.workdays {
+                if let Some(date) = day.date() {
+                    buckets += day.premium_buckets(self.policy.policy_at(date));
+                }
             }
-        } else {
-            None
         }
+        buckets += self.premium_buckets_today();
+        buckets.apply_weekly_overtime(self.policy().premium.weekly_overtime_threshold);
+
+        buckets
+    }
+
+    pub fn premium_buckets_for_month(&self, year: i32, month: u32) -> PremiumBuckets {
+        let mut buckets =
+            self.long_term_registry
+                .premium_buckets_for_month(year, month, &self.policy);
+
+        if let Some(week) = &self.current_work_week {
+            for day in &week.workdays {
+                let Some(date) = day.date() else {
+                    continue;
+                };
+                if date.year() == year && date.month() == month {
+                    buckets += day.premium_buckets(self.policy.policy_at(date));
+                }
+            }
+        }
+
+        buckets
+    }
+
+    /// When to leave to reach each target, respecting the journey, daily and
+    /// break limits. Empty when no journey is in progress.
+    pub fn leave_recommendations(&self) -> Result<Vec<LeaveRecommendation>, ClockedInServiceError> {
+        let Some(current_journey) = &self.current_work_journey else {
+            return Ok(Vec::new());
+        };
+
+        let day_policy = &self
+            .policy
+            .policy_at(current_journey.starting_time.date_naive())
+            .day;
+        let remaining_today = day_policy.expected_hours - self.worked_hours_today();
+        let week_delta = self
+            .current_work_week
+            .as_ref()
+            .map(|week| week.worked_delta())
+            .transpose()
+            .map_err(LongTermRegistryError::IntConversionError)
+            .map_err(ClockedInServiceError::LongTermRegistryError)?
+            .unwrap_or_default();
+        let balance = self.worked_delta_until_today()?;
+
+        let targets = [
+            (LeaveTarget::Today, remaining_today),
+            (
+                LeaveTarget::ThisWeek,
+                remaining_today + TimeDelta::seconds(week_delta.num_seconds()),
+            ),
+            (
+                LeaveTarget::Balance,
+                remaining_today + TimeDelta::seconds(balance.num_seconds()),
+            ),
+        ];
+
+        Ok(targets
+            .into_iter()
+            .map(|(target, remaining)| {
+                LeaveRecommendation::new(
+                    target,
+                    current_journey.starting_time,
+                    &self.current_work_day,
+                    remaining,
+                    self.now(),
+                    day_policy,
+                )
+            })
+            .collect())
+    }
+
+    /// Earliest clock-in that keeps the inter-day rest after the last finished
+    /// day. `None` until the current day is ended, as a clock-out may only be a
+    /// break, or without any finished day.
+    pub fn earliest_clock_in(&self) -> Option<DateTime<Utc>> {
+        if self.current_work_journey.is_some() || !self.current_work_day.is_empty() {
+            return None;
+        }
+
+        Some(self.last_clock_out()? + self.policy().week.min_inter_day_rest)
+    }
+
+    /// Plan for the rest of today to reach the expected hours. `None` when no
+    /// journey is in progress.
+    pub fn day_plan(&self) -> Result<Option<DayPlan>, ClockedInServiceError> {
+        let Some(current_journey) = &self.current_work_journey else {
+            return Ok(None);
+        };
+
+        let day_policy = &self
+            .policy
+            .policy_at(current_journey.starting_time.date_naive())
+            .day;
+        DayPlan::new(
+            current_journey.starting_time,
+            &self.current_work_day,
+            day_policy.expected_hours - self.worked_hours_today(),
+            day_policy,
+        )
+        .map_err(ClockedInServiceError::WorkDayError)
+    }
+
+    /// Live warning for the journey still in progress, raised once it runs
+    /// longer than the configured maximum journey length.
+    pub fn current_journey_violation(&self, now: DateTime<Utc>) -> Option<Violation> {
+        let current_journey = self.current_work_journey.as_ref()?;
+        let journey_index = self.current_work_day.len();
+
+        journey_length_violation(
+            current_journey.starting_time,
+            current_journey.elapsed(now),
+            &self
+                .policy
+                .policy_at(current_journey.starting_time.date_naive())
+                .day,
+        )
+        .map(|violation| violation.with_journeys(vec![journey_index]))
     }
 
     pub fn has_finished_work_day(&self) -> bool {
-        if let Some(week) = self.current_work_week.iter().last() {
+        if let Some(week) = self.current_work_week.iter().next_back() {
             if let Some(day) = week.workdays.last() {
-                let now = chrono::Utc::now();
-
-                return now.date_naive() == day.last_clock_out().date_naive();
+                return day.last_clock_out().is_some_and(|last_clock_out| {
+                    self.now().date_naive() == last_clock_out.date_naive()
+                });
             }
         } else if let Some(week) = self.long_term_registry.history.last() {
             if let Some(day) = week.workdays.last() {
-                let now = chrono::Utc::now();
-
-                return now.date_naive() == day.last_clock_out().date_naive();
+                return day.last_clock_out().is_some_and(|last_clock_out| {
+                    self.now().date_naive() == last_clock_out.date_naive()
+                });
             }
         }
         false
     }
 
-    pub fn display_last_violations(&self) {
-        if let Some(work_week) = &self.current_work_week {
-            if let Some(last_day) = work_week.workdays.last() {
-                for violation in last_day.get_violations() {
-                    match violation {
-                        crate::work_days::IntraDayViolation::ExceddedMaxHours => println!(
-                            "{}{}{}",
-                            "This week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            " -> Worked more than 10 hours.".red().on_bright_white().bold()
-                        ),
-                        crate::work_days::IntraDayViolation::MissingHours => println!(
-                            "{}{}{}",
-                            "This week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            " -> Worked less than 6 hours.".red().on_bright_white().bold()
-                        ),
-                        crate::work_days::IntraDayViolation::ViolatedInterJourneyRest => println!(
-                            "{}{}{}",
-                            "This week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            " -> Inter-journey rest was violated!"
-                                .red()
-                                .on_bright_white()
-                                .bold()
-                        ),
-                        crate::work_days::IntraDayViolation::ExceddedMaxJourneys => println!(
-                            "{}{}{}",
-                            "This week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            " -> Worked more than 5 journeys."
-                                .red()
-                                .on_bright_white()
-                                .bold()
-                        ),
-                    }
-                }
-            }
+    /// Violations recorded for `date` on its day or week, across the registry
+    /// and the current week.
+    pub fn violations_on(&self, date: NaiveDate) -> Vec<Violation> {
+        self.compliance_report(date, date).violations
+    }
 
-            if let Some(_violation) = work_week.get_violation() {
-                println!(
-                    "{}",
-                    " -> Inter-day rest was violated!"
-                        .red()
-                        .on_bright_white()
-                        .bold()
-                );
-            }
-        }else if let Some(work_week) = &self.long_term_registry.history.last() {
-            if let Some(last_day) = work_week.workdays.last() {
-                for violation in last_day.get_violations() {
-                    match violation {
-                        crate::work_days::IntraDayViolation::ExceddedMaxHours => println!(
-                            "{}{}{}",
-                            "Last week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            "-> Worked more than 10 hours.".red().on_bright_white().bold()
-                        ),
-                        crate::work_days::IntraDayViolation::MissingHours => println!(
-                            "{}{}{}",
-                            "Last week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            "-> Worked less than 6 hours.".red().on_bright_white().bold()
-                        ),
-                        crate::work_days::IntraDayViolation::ViolatedInterJourneyRest => println!(
-                            "{}{}{}",
-                            "Last week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            "-> Inter-journey rest was violated!"
-                                .red()
-                                .on_bright_white()
-                                .bold()
-                        ),
-                        crate::work_days::IntraDayViolation::ExceddedMaxJourneys => println!(
-                            "{}{}{}",
-                            "Last week: ".red().on_bright_white().bold(),
-                            last_day.last_clock_out().weekday().to_string().red().on_bright_white().bold(),
-                            "-> Worked more than 5 journeys."
-                                .red()
-                                .on_bright_white()
-                                .bold()
-                        ),
-                    }
-                }
-            }
+    /// Attaches a justification note, and optionally an acknowledgment, to
+    /// the violations of `kind` recorded for `date`.
+    pub fn justify_violation(
+        &mut self,
+        date: NaiveDate,
+        kind: ViolationKind,
+        justification: String,
+        acknowledged: bool,
+    ) -> Result<usize, ClockedInServiceError> {
+        let justified = self
+            .long_term_registry
+            .history
+            .iter_mut()
+            .chain(self.current_work_week.iter_mut())
+            .map(|week| week.justify_violation(date, kind, &justification, acknowledged))
+            .sum();
 
-            if let Some(_violation) = work_week.get_violation() {
-                println!(
-                    "{}",
-                    "Last WorkWeek -> Inter-day rest was violated!"
-                        .red()
-                        .on_bright_white()
-                        .bold()
-                );
-            }
+        if justified == 0 {
+            return Err(ClockedInServiceError::ViolationNotFound(date, kind));
         }
+        Ok(justified)
+    }
+
+    /// Violations recorded between `from` and `to`, inclusive, across the
+    /// registry and the current week.
+    pub fn compliance_report(&self, from: NaiveDate, to: NaiveDate) -> ComplianceReport {
+        ComplianceReport::new(
+            from,
+            to,
+            self.long_term_registry
+                .history
+                .iter()
+                .chain(self.current_work_week.iter()),
+        )
     }
 
     fn serialize_to_json(&self) -> Result<String, ClockedInServiceError> {
@@ -354,6 +1084,10 @@ impl ClockedInService {
     }
 }
 
+fn default_clock() -> Box<dyn Clock> {
+    Box::new(SystemClock)
+}
+
 fn same_work_day(starting_time: DateTime<Utc>, last_clock_out: DateTime<Utc>) -> bool {
     (starting_time.year() == last_clock_out.year())
         && (starting_time.month() == last_clock_out.month())
@@ -384,3 +1118,782 @@ fn open_long_term_registry_file_to_read() -> Result<std::fs::File, ClockedInServ
         .map_err(|_| ClockedInServiceError::LongTermRegistryOpenError)?;
     Ok(file)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta, TimeZone, Utc};
+
+    use crate::{
+        clock::FixedClock,
+        long_term_registry::LongTermRegistryError,
+        policy::WorkPolicy,
+        recommendations::LeaveTarget,
+        violations::ViolationKind,
+        work_days::WorkDayError,
+        work_journey::{IncompleteWorkJourney, WorkJourney},
+        work_week::tests::initialize_fixed_week,
+    };
+
+    use super::{
+        ClockedInService, ClockedInServiceError, ComplianceAction, CorrectionAction,
+        EnforcementMode, PunchOutcome, ServiceSettings, StaleJourneyRecovery,
+    };
+
+    #[test]
+    fn leave_recommendations_account_for_the_week_delta() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 8, 12, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 13, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 8, 17, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 9, 8, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 9, 12, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 9, 13, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 9, 14, 0, 0).unwrap(),
+        )));
+
+        let recommendations = clockedin_service.leave_recommendations().unwrap();
+
+        assert_eq!(LeaveTarget::Today, recommendations[0].target);
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 7, 9, 17, 0, 0).unwrap(),
+            recommendations[0].leave_at
+        );
+        assert_eq!(
+            Utc.with_ymd_and_hms(2024, 7, 9, 18, 0, 0).unwrap(),
+            recommendations[1].leave_at
+        );
+        assert!(recommendations[1].is_reachable());
+    }
+
+    #[test]
+    fn contract_changes_keep_past_deltas() {
+        let mut clockedin_service = ClockedInService::new();
+        let mut part_time = WorkPolicy::default();
+        part_time.day.expected_hours = TimeDelta::hours(6);
+        clockedin_service
+            .add_policy_version(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), part_time);
+        clockedin_service.add_policy_version(
+            NaiveDate::from_ymd_opt(2024, 7, 10).unwrap(),
+            WorkPolicy::default(),
+        );
+
+        for (day, end_hour) in [(8, 15), (9, 15), (10, 17)] {
+            clockedin_service
+                .clock_in(Utc.with_ymd_and_hms(2024, 7, day, 8, 0, 0).unwrap())
+                .unwrap();
+            clockedin_service
+                .clock_out(Utc.with_ymd_and_hms(2024, 7, day, 11, 0, 0).unwrap())
+                .unwrap();
+            clockedin_service
+                .clock_in(Utc.with_ymd_and_hms(2024, 7, day, 12, 0, 0).unwrap())
+                .unwrap();
+            clockedin_service
+                .clock_out_and_end_work_day(
+                    Utc.with_ymd_and_hms(2024, 7, day, end_hour, 0, 0).unwrap(),
+                )
+                .unwrap();
+        }
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 9, 18, 0, 0).unwrap(),
+        )));
+
+        assert_eq!(
+            0,
+            clockedin_service
+                .worked_delta_until_today()
+                .unwrap()
+                .num_seconds()
+        );
+        assert_eq!(
+            TimeDelta::hours(6),
+            clockedin_service.policy().day.expected_hours
+        );
+        assert_eq!(2, clockedin_service.policy_schedule().get_versions().len());
+    }
+
+    #[test]
+    fn setting_a_policy_keeps_past_premiums() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 6, 10, 20, 0, 0).unwrap(),
+        )));
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 6, 10, 8, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 6, 10, 18, 0, 0).unwrap())
+            .unwrap();
+        let june_premiums = clockedin_service.premium_buckets_for_month(2024, 6);
+
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 10, 12, 0, 0).unwrap(),
+        )));
+        let mut policy = WorkPolicy::default();
+        policy.premium.daily_overtime = !policy.premium.daily_overtime;
+        policy.premium.overtime_multiplier = 2.0;
+        clockedin_service.set_policy(policy.clone());
+
+        assert_eq!(&policy, clockedin_service.policy());
+        assert_eq!(
+            &WorkPolicy::default(),
+            clockedin_service
+                .policy_schedule()
+                .policy_at(NaiveDate::from_ymd_opt(2024, 6, 10).unwrap())
+        );
+        assert_eq!(
+            june_premiums,
+            clockedin_service.premium_buckets_for_month(2024, 6)
+        );
+    }
+
+    #[test]
+    fn weekly_premiums_include_the_day_in_progress() {
+        let mut clockedin_service = ClockedInService::new();
+        let mut policy = WorkPolicy::default();
+        policy.premium.weekly_overtime_threshold = TimeDelta::hours(10);
+        clockedin_service.add_policy_version(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), policy);
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 9, 14, 0, 0).unwrap(),
+        )));
+
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 8, 17, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 9, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 9, 13, 0, 0).unwrap())
+            .unwrap();
+
+        let buckets = clockedin_service.premium_buckets_this_week();
+        assert_eq!(TimeDelta::hours(12).num_seconds(), buckets.worked_seconds());
+        assert_eq!(TimeDelta::hours(2).num_seconds(), buckets.overtime);
+    }
+
+    #[test]
+    fn average_weekly_hours_leave_out_the_week_in_progress() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.long_term_registry.history = vec![
+            initialize_fixed_week(2024, 7, &[1, 2, 3, 4, 5], 6),
+            initialize_fixed_week(2024, 7, &[8, 9, 10, 11, 12], 8),
+        ];
+        clockedin_service.current_work_week = Some(initialize_fixed_week(2024, 7, &[15], 2));
+
+        assert_eq!(
+            Some((TimeDelta::hours(35), 2)),
+            clockedin_service.average_weekly_hours()
+        );
+    }
+
+    #[test]
+    fn weeks_keep_every_day_from_monday_to_sunday() {
+        let mut clockedin_service = ClockedInService::new();
+
+        for day in 8..=14 {
+            clockedin_service
+                .clock_in(Utc.with_ymd_and_hms(2024, 7, day, 9, 0, 0).unwrap())
+                .unwrap();
+            clockedin_service
+                .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, day, 13, 0, 0).unwrap())
+                .unwrap();
+        }
+
+        let worked_days = clockedin_service.worked_hours_this_week();
+        assert_eq!(7, worked_days.len());
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2024, 7, 14).unwrap(),
+            worked_days[6].0
+        );
+    }
+
+    #[test]
+    fn ending_a_day_of_another_week_starts_a_new_week() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 14, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 14, 13, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service.current_work_day = vec![WorkJourney::new(
+            Utc.with_ymd_and_hms(2024, 7, 15, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 15, 13, 0, 0).unwrap(),
+        )
+        .unwrap()];
+
+        clockedin_service.end_work_day().unwrap();
+
+        assert_eq!(1, clockedin_service.long_term_registry.history.len());
+        assert_eq!(
+            vec![(
+                NaiveDate::from_ymd_opt(2024, 7, 15).unwrap(),
+                TimeDelta::hours(4)
+            )],
+            clockedin_service.worked_hours_this_week()
+        );
+    }
+
+    #[test]
+    fn closed_periods_are_protected() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 7, day).unwrap();
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 10, 12, 0, 0).unwrap(),
+        )));
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 8, 17, 0, 0).unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            clockedin_service.close_period(date(11), String::new()),
+            Err(ClockedInServiceError::PeriodClosedInTheFuture(_))
+        ));
+        clockedin_service
+            .close_period(date(8), String::new())
+            .unwrap();
+
+        assert!(clockedin_service
+            .add_balance_adjustment(date(8), TimeDelta::hours(1), String::new())
+            .is_err());
+        assert!(clockedin_service
+            .set_opening_balance(date(1), TimeDelta::hours(1), String::new())
+            .is_err());
+        clockedin_service
+            .add_balance_adjustment(date(9), TimeDelta::hours(1), String::new())
+            .unwrap();
+        assert_eq!(2, clockedin_service.ledger().len());
+    }
+
+    #[test]
+    fn closed_periods_keep_out_open_and_later_work() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 7, day).unwrap();
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 10, 20, 0, 0).unwrap(),
+        )));
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 10, 12, 0, 0).unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            clockedin_service.close_period(date(10), String::new()),
+            Err(ClockedInServiceError::PeriodHasOpenWork(open_date)) if open_date == date(10)
+        ));
+        assert!(clockedin_service.settlements().is_empty());
+
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 10, 13, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 10, 17, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .close_period(date(10), String::new())
+            .unwrap();
+
+        assert!(matches!(
+            clockedin_service.clock_in(Utc.with_ymd_and_hms(2024, 7, 10, 18, 0, 0).unwrap()),
+            Err(ClockedInServiceError::LongTermRegistryError(
+                LongTermRegistryError::PeriodAlreadyClosed(_)
+            ))
+        ));
+        assert!(!clockedin_service.is_clocked_in());
+
+        clockedin_service.current_work_journey = Some(IncompleteWorkJourney::new(
+            Utc.with_ymd_and_hms(2024, 7, 10, 18, 0, 0).unwrap(),
+        ));
+        assert!(matches!(
+            clockedin_service
+                .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 10, 19, 0, 0).unwrap()),
+            Err(ClockedInServiceError::LongTermRegistryError(
+                LongTermRegistryError::PeriodAlreadyClosed(_)
+            ))
+        ));
+        assert!(clockedin_service.current_work_day.is_empty());
+        assert_eq!(
+            0,
+            clockedin_service
+                .worked_delta_until_today()
+                .unwrap()
+                .num_seconds()
+        );
+    }
+
+    #[test]
+    fn days_stored_without_journeys_are_skipped() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 9, 14, 0, 0).unwrap(),
+        )));
+        let mut week = initialize_fixed_week(2024, 7, &[8], 8);
+        week.workdays
+            .push(serde_json::from_str(r#"{"journeys": [], "worked_seconds": 0}"#).unwrap());
+        clockedin_service.long_term_registry.history = vec![week.clone()];
+        clockedin_service.current_work_week = Some(week);
+
+        assert_eq!(1, clockedin_service.worked_hours_this_week().len());
+        assert!(!clockedin_service.has_finished_work_day());
+        assert_eq!(
+            None,
+            clockedin_service.pending_rollover(Utc.with_ymd_and_hms(2024, 7, 9, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            TimeDelta::hours(8).num_seconds(),
+            clockedin_service
+                .premium_buckets_this_week()
+                .worked_seconds()
+        );
+        assert_eq!(
+            TimeDelta::hours(16).num_seconds(),
+            clockedin_service
+                .premium_buckets_for_month(2024, 7)
+                .worked_seconds()
+        );
+    }
+
+    #[test]
+    fn invalid_days_are_not_ended() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 8, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 8, 12, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 11, 0, 0).unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            clockedin_service
+                .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 8, 13, 0, 0).unwrap()),
+            Err(ClockedInServiceError::WorkDayError(
+                WorkDayError::OverlappingJourneys(1)
+            ))
+        ));
+        assert!(clockedin_service.is_clocked_in());
+        assert_eq!(1, clockedin_service.current_work_day.len());
+        assert!(clockedin_service.current_work_week.is_none());
+    }
+
+    #[test]
+    fn earliest_clock_in_keeps_the_inter_day_rest() {
+        let mut clockedin_service = ClockedInService::new();
+        assert!(clockedin_service.earliest_clock_in().is_none());
+
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 12, 8, 0, 0).unwrap())
+            .unwrap();
+        assert!(clockedin_service.earliest_clock_in().is_none());
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 12, 12, 0, 0).unwrap())
+            .unwrap();
+        assert!(clockedin_service.earliest_clock_in().is_none());
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 12, 13, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_week(Utc.with_ymd_and_hms(2024, 7, 12, 22, 0, 0).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 7, 13, 9, 0, 0).unwrap()),
+            clockedin_service.earliest_clock_in()
+        );
+    }
+
+    #[test]
+    fn enforcement_modes_guard_actions_with_violations() {
+        let late_clock_out = Utc.with_ymd_and_hms(2024, 7, 8, 21, 0, 0).unwrap();
+        let early_clock_in = Utc.with_ymd_and_hms(2024, 7, 9, 7, 0, 0).unwrap();
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 8, 12, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 13, 0, 0).unwrap())
+            .unwrap();
+
+        let violations = clockedin_service.check_action(ComplianceAction::EndDay, late_clock_out);
+        assert!(violations
+            .iter()
+            .any(|violation| violation.kind == ViolationKind::ExceededMaxHours));
+
+        clockedin_service.set_settings(ServiceSettings {
+            enforcement_mode: EnforcementMode::Confirm,
+            ..Default::default()
+        });
+        assert!(matches!(
+            clockedin_service.clock_out_and_end_work_day(late_clock_out),
+            Err(ClockedInServiceError::JustificationRequired(_))
+        ));
+        clockedin_service.justify_next_action("Emergency deploy".to_string());
+        clockedin_service
+            .clock_out_and_end_work_day(late_clock_out)
+            .unwrap();
+        assert_eq!(1, clockedin_service.compliance_overrides().len());
+        assert_eq!(
+            "Emergency deploy",
+            clockedin_service.compliance_overrides()[0].justification
+        );
+        assert!(clockedin_service
+            .violations_on(late_clock_out.date_naive())
+            .iter()
+            .filter(|violation| violation.kind == ViolationKind::ExceededMaxHours)
+            .all(|violation| violation.justification == Some("Emergency deploy".to_string())));
+
+        assert!(clockedin_service
+            .check_action(ComplianceAction::ClockIn, early_clock_in)
+            .iter()
+            .any(|violation| violation.kind == ViolationKind::InterDayRest));
+        clockedin_service.set_settings(ServiceSettings {
+            enforcement_mode: EnforcementMode::Strict,
+            ..Default::default()
+        });
+        assert!(matches!(
+            clockedin_service.clock_in(early_clock_in),
+            Err(ClockedInServiceError::ComplianceRefused(_))
+        ));
+        clockedin_service.set_settings(ServiceSettings {
+            enforcement_mode: EnforcementMode::Advisory,
+            ..Default::default()
+        });
+        assert!(clockedin_service.clock_in(early_clock_in).is_ok());
+    }
+
+    #[test]
+    fn lunch_breaks_are_never_refused() {
+        for enforcement_mode in [EnforcementMode::Strict, EnforcementMode::Confirm] {
+            let mut clockedin_service = ClockedInService::new();
+            clockedin_service.set_settings(ServiceSettings {
+                enforcement_mode,
+                ..Default::default()
+            });
+            clockedin_service
+                .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 8, 0, 0).unwrap())
+                .unwrap();
+
+            let lunch = Utc.with_ymd_and_hms(2024, 7, 8, 12, 30, 0).unwrap();
+            assert!(clockedin_service
+                .check_action(ComplianceAction::ClockOut, lunch)
+                .is_empty());
+            clockedin_service.clock_out(lunch).unwrap();
+            clockedin_service
+                .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 13, 30, 0).unwrap())
+                .unwrap();
+            assert!(clockedin_service.compliance_overrides().is_empty());
+        }
+
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.set_settings(ServiceSettings {
+            enforcement_mode: EnforcementMode::Strict,
+            ..Default::default()
+        });
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 8, 0, 0).unwrap())
+            .unwrap();
+        let long_journey_end = Utc.with_ymd_and_hms(2024, 7, 8, 15, 0, 0).unwrap();
+        assert!(clockedin_service
+            .check_action(ComplianceAction::ClockOut, long_journey_end)
+            .iter()
+            .any(|violation| violation.kind == ViolationKind::ExceededMaxJourneyLength));
+        assert!(clockedin_service.clock_out(long_journey_end).is_ok());
+    }
+
+    #[test]
+    fn justifications_are_kept_until_the_action_succeeds() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 13, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 8, 22, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service.set_settings(ServiceSettings {
+            enforcement_mode: EnforcementMode::Confirm,
+            require_rollover_confirmation: true,
+            ..Default::default()
+        });
+        let early_clock_in = Utc.with_ymd_and_hms(2024, 7, 9, 7, 0, 0).unwrap();
+
+        clockedin_service.justify_next_action("Inventory".to_string());
+        assert!(matches!(
+            clockedin_service.clock_in(early_clock_in),
+            Err(ClockedInServiceError::RolloverConfirmationRequired(_))
+        ));
+        assert!(clockedin_service.compliance_overrides().is_empty());
+
+        clockedin_service
+            .roll_over_pending_work(early_clock_in)
+            .unwrap();
+        clockedin_service.clock_in(early_clock_in).unwrap();
+        assert_eq!(1, clockedin_service.compliance_overrides().len());
+        assert_eq!(
+            "Inventory",
+            clockedin_service.compliance_overrides()[0].justification
+        );
+    }
+
+    #[test]
+    fn violations_can_be_justified_and_acknowledged() {
+        let date = NaiveDate::from_ymd_opt(2024, 7, 8).unwrap();
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 8, 12, 0, 0).unwrap())
+            .unwrap();
+
+        let justified = clockedin_service
+            .justify_violation(
+                date,
+                ViolationKind::MissingHours,
+                "Doctor appointment".to_string(),
+                true,
+            )
+            .unwrap();
+
+        assert_eq!(1, justified);
+        let violation = clockedin_service
+            .violations_on(date)
+            .into_iter()
+            .find(|violation| violation.kind == ViolationKind::MissingHours)
+            .unwrap();
+        assert!(violation.acknowledged);
+        assert!(violation.to_string().contains("Doctor appointment"));
+        assert!(matches!(
+            clockedin_service.justify_violation(
+                date,
+                ViolationKind::ExceededMaxHours,
+                String::new(),
+                false
+            ),
+            Err(ClockedInServiceError::ViolationNotFound(_, _))
+        ));
+    }
+
+    #[test]
+    fn finished_work_day_follows_the_injected_clock() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 8, 17, 0, 0).unwrap())
+            .unwrap();
+
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 8, 23, 59, 59).unwrap(),
+        )));
+        assert!(clockedin_service.has_finished_work_day());
+
+        clockedin_service.set_clock(Box::new(FixedClock::new(
+            Utc.with_ymd_and_hms(2024, 7, 9, 0, 0, 0).unwrap(),
+        )));
+        assert!(!clockedin_service.has_finished_work_day());
+    }
+
+    #[test]
+    fn clock_in_on_new_date_closes_pending_day() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 8, 17, 0, 0).unwrap())
+            .unwrap();
+
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 9, 9, 0, 0).unwrap())
+            .unwrap();
+
+        let week = clockedin_service.worked_hours_this_week();
+        assert_eq!(1, week.len());
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 7, 8).unwrap(), week[0].0);
+        assert!(clockedin_service.current_work_day.is_empty());
+        assert!(clockedin_service.current_work_journey.is_some());
+    }
+
+    #[test]
+    fn clock_in_on_new_week_rolls_the_week() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 12, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out_and_end_work_day(Utc.with_ymd_and_hms(2024, 7, 12, 17, 0, 0).unwrap())
+            .unwrap();
+
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 15, 9, 0, 0).unwrap())
+            .unwrap();
+
+        assert_eq!(1, clockedin_service.long_term_registry.history.len());
+        assert!(clockedin_service.worked_hours_this_week().is_empty());
+    }
+
+    #[test]
+    fn rollover_confirmation_is_required_when_configured() {
+        let mut clockedin_service = ClockedInService::new();
+        clockedin_service.set_settings(ServiceSettings {
+            require_rollover_confirmation: true,
+            ..Default::default()
+        });
+        clockedin_service
+            .clock_in(Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap())
+            .unwrap();
+        clockedin_service
+            .clock_out(Utc.with_ymd_and_hms(2024, 7, 8, 17, 0, 0).unwrap())
+            .unwrap();
+        let next_clock_in = Utc.with_ymd_and_hms(2024, 7, 9, 9, 0, 0).unwrap();
+
+        let result = clockedin_service.clock_in(next_clock_in);
+        assert!(matches!(
+            result,
+            Err(ClockedInServiceError::RolloverConfirmationRequired(date))
+                if date == NaiveDate::from_ymd_opt(2024, 7, 8).unwrap()
+        ));
+
+        clockedin_service
+            .roll_over_pending_work(next_clock_in)
+            .unwrap();
+        assert!(clockedin_service.clock_in(next_clock_in).is_ok());
+        assert_eq!(1, clockedin_service.worked_hours_this_week().len());
+    }
+
+    #[test]
+    fn stale_journey_detection() {
+        let mut clockedin_service = ClockedInService::new();
+        let starting_time = Utc.with_ymd_and_hms(2024, 7, 8, 6, 0, 0).unwrap();
+        clockedin_service.clock_in(starting_time).unwrap();
+
+        assert!(clockedin_service
+            .stale_journey(starting_time + TimeDelta::hours(10))
+            .is_none());
+        let stale_by_limit = clockedin_service
+            .stale_journey(starting_time + TimeDelta::hours(17))
+            .unwrap();
+        assert!(!stale_by_limit.crosses_day_cutover);
+        assert_eq!(TimeDelta::hours(17), stale_by_limit.elapsed);
+
+        let mut night_service = ClockedInService::new();
+        let night_start = Utc.with_ymd_and_hms(2024, 7, 8, 20, 0, 0).unwrap();
+        night_service.clock_in(night_start).unwrap();
+        let stale_by_cutover = night_service
+            .stale_journey(night_start + TimeDelta::hours(5))
+            .unwrap();
+        assert!(stale_by_cutover.crosses_day_cutover);
+    }
+
+    #[test]
+    fn stale_journey_recovery() {
+        let starting_time = Utc.with_ymd_and_hms(2024, 7, 8, 9, 0, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 7, 9, 8, 0, 0).unwrap();
+
+        let mut expected_end_service = ClockedInService::new();
+        expected_end_service.clock_in(starting_time).unwrap();
+        expected_end_service
+            .recover_stale_journey(StaleJourneyRecovery::CloseAtExpectedEnd, now)
+            .unwrap();
+        assert_eq!(
+            TimeDelta::hours(8),
+            expected_end_service.worked_hours_today()
+        );
+        assert!(expected_end_service.current_work_day[0].is_corrected());
+
+        let mut user_time_service = ClockedInService::new();
+        user_time_service.clock_in(starting_time).unwrap();
+        let ending_time = starting_time + TimeDelta::hours(5);
+        user_time_service
+            .recover_stale_journey(StaleJourneyRecovery::CloseAt(ending_time), now)
+            .unwrap();
+        assert_eq!(TimeDelta::hours(5), user_time_service.worked_hours_today());
+        assert_eq!(
+            CorrectionAction::ClosedAt(ending_time),
+            user_time_service.corrections()[0].action
+        );
+
+        let mut discard_service = ClockedInService::new();
+        discard_service.clock_in(starting_time).unwrap();
+        discard_service
+            .recover_stale_journey(StaleJourneyRecovery::Discard, now)
+            .unwrap();
+        assert!(discard_service.current_work_journey.is_none());
+        assert!(discard_service.current_work_day.is_empty());
+        assert_eq!(
+            CorrectionAction::Discarded,
+            discard_service.corrections()[0].action
+        );
+    }
+
+    #[test]
+    fn punch_toggles_and_rolls_days_and_weeks() {
+        let mut clockedin_service = ClockedInService::new();
+        let punches = [
+            (2024, 7, 12, 9),
+            (2024, 7, 12, 12),
+            (2024, 7, 12, 13),
+            (2024, 7, 12, 18),
+            (2024, 7, 15, 9),
+        ];
+
+        let outcomes: Vec<PunchOutcome> = punches
+            .iter()
+            .map(|(year, month, day, hour)| {
+                clockedin_service
+                    .punch(
+                        Utc.with_ymd_and_hms(*year, *month, *day, *hour, 0, 0)
+                            .unwrap(),
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                PunchOutcome::ClockedIn,
+                PunchOutcome::ClockedOut,
+                PunchOutcome::ClockedIn,
+                PunchOutcome::ClockedOut,
+                PunchOutcome::ClockedIn,
+            ],
+            outcomes
+        );
+        assert_eq!(1, clockedin_service.long_term_registry.history.len());
+        assert_eq!(
+            TimeDelta::hours(8).num_seconds(),
+            clockedin_service.long_term_registry.worked_hours()
+        );
+        assert!(clockedin_service.current_work_journey.is_some());
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::{
+    violations::{Violation, ViolationKind},
+    work_days::WorkDay,
+    work_week::WorkWeek,
+};
+
+/// Every violation recorded between two dates, inclusive, for audits.
+#[derive(Clone, Debug, Serialize)]
+pub struct ComplianceReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub violations: Vec<Violation>,
+    pub counts: BTreeMap<ViolationKind, usize>,
+}
+
+impl ComplianceReport {
+    pub fn new<'a>(
+        from: NaiveDate,
+        to: NaiveDate,
+        weeks: impl IntoIterator<Item = &'a WorkWeek>,
+    ) -> ComplianceReport {
+        let mut violations: Vec<Violation> = weeks
+            .into_iter()
+            .flat_map(|week| {
+                week.workdays
+                    .iter()
+                    .flat_map(WorkDay::get_violations)
+                    .chain(week.get_violations())
+                    .collect::<Vec<Violation>>()
+            })
+            .filter(|violation| violation.date >= from && violation.date <= to)
+            .collect();
+        violations.sort_by_key(|violation| (violation.date, violation.kind));
+
+        let mut counts = BTreeMap::new();
+        for violation in &violations {
+            *counts.entry(violation.kind).or_insert(0) += 1;
+        }
+
+        ComplianceReport {
+            from,
+            to,
+            violations,
+            counts,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("kind,date,severity,measured,limit,justification,acknowledged\n");
+        for violation in &self.violations {
+            let fields = [
+                violation.kind.name().to_string(),
+                violation.date.to_string(),
+                violation.severity.to_string(),
+                violation.measured.to_string(),
+                violation.limit.to_string(),
+                violation.justification.clone().unwrap_or_default(),
+                violation.acknowledged.to_string(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Display for ComplianceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Compliance report from {} to {}", self.from, self.to)?;
+        for violation in &self.violations {
+            writeln!(f, " * {}", violation)?;
+        }
+        writeln!(f, "Violations per rule:")?;
+        for (kind, count) in &self.counts {
+            writeln!(f, " * {}: {}", kind.description(), count)?;
+        }
+        Ok(())
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        policy::WeekPolicy, violations::ViolationKind, work_week::tests::initialize_fixed_week,
+    };
+
+    use super::ComplianceReport;
+
+    #[test]
+    fn report_filters_by_date_and_counts_per_rule() {
+        let previous_week = initialize_fixed_week(2024, 7, &[1, 2, 3], 4);
+        let mut week = initialize_fixed_week(2024, 7, &[8, 9, 10, 11, 12], 9);
+        week.evaluate_weekly_rules(std::slice::from_ref(&previous_week), &WeekPolicy::default());
+        week.justify_violation(
+            NaiveDate::from_ymd_opt(2024, 7, 12).unwrap(),
+            ViolationKind::ExceededMaxWeeklyHours,
+            "Release, with hotfix",
+            true,
+        );
+
+        let report = ComplianceReport::new(
+            NaiveDate::from_ymd_opt(2024, 7, 2).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 7, 12).unwrap(),
+            [&previous_week, &week],
+        );
+
+        assert_eq!(Some(&2), report.counts.get(&ViolationKind::MissingHours));
+        assert_eq!(
+            Some(&5),
+            report.counts.get(&ViolationKind::ExceededMaxJourneyLength)
+        );
+        assert_eq!(
+            Some(&1),
+            report.counts.get(&ViolationKind::ExceededMaxWeeklyHours)
+        );
+        assert!(report.to_csv().contains(
+            "exceeded_max_weekly_hours,2024-07-12,major,45h:00m,44h:00m,\"Release, with hotfix\",true"
+        ));
+        let json = report.to_json().unwrap();
+        assert!(json.contains("\"missing_hours\": 2"));
+        assert!(json.contains("\"kind\": \"exceeded_max_weekly_hours\""));
+        assert_eq!(
+            ViolationKind::MissingHours,
+            serde_json::from_str("\"MissingHours\"").unwrap()
+        );
+    }
+}
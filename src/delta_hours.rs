@@ -35,6 +35,11 @@ impl DeltaHours {
         }
     }
 
+    /// Signed seconds, positive when hours are missing.
+    pub fn num_seconds(&self) -> i64 {
+        self.original_delta
+    }
+
     pub fn is_zero(&self) -> bool {
         self.unsigned_delta == 0
     }
@@ -95,6 +100,16 @@ impl Display for DeltaHours {
     }
 }
 
+pub fn format_seconds(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    format!(
+        "{}{}h:{:02}m",
+        sign,
+        seconds.abs() / 3600,
+        (seconds.abs() % 3600) / 60
+    )
+}
+
 impl Default for DeltaHours {
     fn default() -> Self {
         Self {
@@ -104,3 +119,15 @@ impl Default for DeltaHours {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::format_seconds;
+
+    #[test]
+    fn formatted_seconds_keep_their_sign() {
+        assert_eq!("0h:30m", format_seconds(1800));
+        assert_eq!("-0h:30m", format_seconds(-1800));
+        assert_eq!("-2h:05m", format_seconds(-7500));
+    }
+}
@@ -0,0 +1,363 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+
+use crate::{
+    clockedin_service::StaleJourney,
+    delta_hours::format_seconds,
+    work_days::{WorkDay, WorkDayError},
+    work_journey::WorkJourney,
+    work_week::{WorkWeek, MAX_DAYS_PER_WEEK},
+};
+
+/// Where a problem was found. Weeks are counted from the start of the
+/// history, the current week being the last one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegistryLocation {
+    Week(usize),
+    Day { week: usize, day: usize },
+    CurrentDay,
+    OpenJourney,
+}
+
+impl Display for RegistryLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryLocation::Week(week) => write!(f, "week {}", week),
+            RegistryLocation::Day { week, day } => write!(f, "week {}, day {}", week, day),
+            RegistryLocation::CurrentDay => write!(f, "current day"),
+            RegistryLocation::OpenJourney => write!(f, "open journey"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum IntegrityProblem {
+    EmptyDay,
+    InvalidJourneys(WorkDayError),
+    UnorderedDay {
+        date: NaiveDate,
+        previous_date: NaiveDate,
+    },
+    TooManyDays(usize),
+    WorkedSecondsMismatch {
+        cached: i64,
+        counted: i64,
+    },
+    StaleOpenJourney(StaleJourney),
+}
+
+impl IntegrityProblem {
+    /// Safe problems are repaired without changing any recorded punch.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            IntegrityProblem::EmptyDay | IntegrityProblem::WorkedSecondsMismatch { .. }
+        )
+    }
+}
+
+impl Display for IntegrityProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityProblem::EmptyDay => write!(f, "Day without journeys"),
+            IntegrityProblem::InvalidJourneys(err) => write!(f, "{}", err),
+            IntegrityProblem::UnorderedDay {
+                date,
+                previous_date,
+            } => write!(
+                f,
+                "Day {} is not after the previous day {}",
+                date, previous_date
+            ),
+            IntegrityProblem::TooManyDays(days) => write!(
+                f,
+                "Week has {} days, more than the {} allowed",
+                days, MAX_DAYS_PER_WEEK
+            ),
+            IntegrityProblem::WorkedSecondsMismatch { cached, counted } => write!(
+                f,
+                "Cached worked time {} does not match the journeys ({})",
+                format_seconds(*cached),
+                format_seconds(*counted)
+            ),
+            IntegrityProblem::StaleOpenJourney(stale_journey) => write!(
+                f,
+                "Journey open since {} ({} ago)",
+                stale_journey.starting_time,
+                format_seconds(stale_journey.elapsed.num_seconds())
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IntegrityIssue {
+    pub location: RegistryLocation,
+    pub problem: IntegrityProblem,
+    /// Found on a day of a closed period, which repairs leave untouched.
+    pub in_closed_period: bool,
+}
+
+impl IntegrityIssue {
+    pub fn is_repairable(&self) -> bool {
+        self.problem.is_repairable() && !self.in_closed_period
+    }
+}
+
+impl Display for IntegrityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}{}",
+            self.location,
+            self.problem,
+            if self.in_closed_period {
+                " [closed period]"
+            } else if self.problem.is_repairable() {
+                " [repairable]"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// Checks the recorded weeks, in order, for broken invariants. Issues on
+/// days up to `closed_until` are flagged as in a closed period.
+pub fn check_weeks<'a>(
+    weeks: impl IntoIterator<Item = &'a WorkWeek>,
+    closed_until: Option<NaiveDate>,
+) -> Vec<IntegrityIssue> {
+    let mut issues = Vec::new();
+    let mut previous_date: Option<NaiveDate> = None;
+
+    for (week_index, week) in weeks.into_iter().enumerate() {
+        if week.workdays.len() > MAX_DAYS_PER_WEEK {
+            issues.push(IntegrityIssue {
+                location: RegistryLocation::Week(week_index),
+                problem: IntegrityProblem::TooManyDays(week.workdays.len()),
+                in_closed_period: is_closed_week(week, closed_until),
+            });
+        }
+
+        for (day_index, day) in week.workdays.iter().enumerate() {
+            let location = RegistryLocation::Day {
+                week: week_index,
+                day: day_index,
+            };
+            match WorkDay::validate(day.get_journeys()) {
+                Err(WorkDayError::EmptyDay) => {
+                    issues.push(IntegrityIssue {
+                        location,
+                        problem: IntegrityProblem::EmptyDay,
+                        in_closed_period: is_closed_week(week, closed_until),
+                    });
+                    continue;
+                }
+                Err(err) => issues.push(IntegrityIssue {
+                    location,
+                    problem: IntegrityProblem::InvalidJourneys(err),
+                    in_closed_period: is_closed_day(day, closed_until),
+                }),
+                Ok(()) => (),
+            }
+
+            let Some(date) = day.date() else {
+                continue;
+            };
+            let in_closed_period = is_closed_day(day, closed_until);
+            if let Some(previous_date) = previous_date.filter(|previous| *previous >= date) {
+                issues.push(IntegrityIssue {
+                    location,
+                    problem: IntegrityProblem::UnorderedDay {
+                        date,
+                        previous_date,
+                    },
+                    in_closed_period,
+                });
+            }
+            previous_date = Some(date);
+
+            let counted = day.counted_hours();
+            if counted != day.worked_hours() {
+                issues.push(IntegrityIssue {
+                    location,
+                    problem: IntegrityProblem::WorkedSecondsMismatch {
+                        cached: day.worked_hours(),
+                        counted,
+                    },
+                    in_closed_period,
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+/// Checks the journeys of the day still in progress, which may be empty.
+pub fn check_current_day(journeys: &[WorkJourney]) -> Vec<IntegrityIssue> {
+    match WorkDay::validate(journeys) {
+        Err(WorkDayError::EmptyDay) | Ok(()) => Vec::new(),
+        Err(err) => vec![IntegrityIssue {
+            location: RegistryLocation::CurrentDay,
+            problem: IntegrityProblem::InvalidJourneys(err),
+            in_closed_period: false,
+        }],
+    }
+}
+
+/// Drops empty days and recounts cached worked time under the clock policy
+/// recorded with each day, returning how many problems were repaired. Days up
+/// to `closed_until` are left untouched. Other problems need a manual fix.
+pub fn repair_weeks(weeks: &mut [WorkWeek], closed_until: Option<NaiveDate>) -> usize {
+    let mut repaired = 0;
+
+    for week in weeks.iter_mut() {
+        if is_closed_week(week, closed_until) {
+            continue;
+        }
+        let days = week.workdays.len();
+        week.workdays.retain(|day| !day.get_journeys().is_empty());
+        repaired += days - week.workdays.len();
+
+        for day in week.workdays.iter_mut() {
+            if !is_closed_day(day, closed_until) && day.counted_hours() != day.worked_hours() {
+                day.recount();
+                repaired += 1;
+            }
+        }
+    }
+
+    repaired
+}
+
+fn is_closed_day(day: &WorkDay, closed_until: Option<NaiveDate>) -> bool {
+    closed_until.is_some_and(|closed_on| day.date().is_some_and(|date| date <= closed_on))
+}
+
+/// Whether every dated day of the week is in a closed period.
+fn is_closed_week(week: &WorkWeek, closed_until: Option<NaiveDate>) -> bool {
+    week.workdays
+        .iter()
+        .rev()
+        .find(|day| !day.get_journeys().is_empty())
+        .is_some_and(|day| is_closed_day(day, closed_until))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    use crate::{
+        policy::{ClockTimePolicy, DayPolicy, WeekPolicy},
+        work_days::{WorkDay, WorkDayError},
+        work_journey::WorkJourney,
+        work_week::{tests::initialize_fixed_week, WorkWeek},
+    };
+
+    use super::{check_current_day, check_weeks, repair_weeks, IntegrityProblem, RegistryLocation};
+
+    #[test]
+    fn check_reports_locations_and_repairs_safe_problems() {
+        let first_week = initialize_fixed_week(2024, 7, &[8, 9], 8);
+        let mut second_week = initialize_fixed_week(2024, 7, &[9, 10], 8);
+        let mut tampered_day = serde_json::to_value(&second_week.workdays[1]).unwrap();
+        tampered_day["worked_seconds"] = TimeDelta::hours(3).num_seconds().into();
+        second_week.workdays[1] = serde_json::from_value(tampered_day).unwrap();
+        second_week
+            .workdays
+            .push(serde_json::from_str(r#"{"journeys": [], "worked_seconds": 0}"#).unwrap());
+        let mut weeks = vec![first_week, second_week];
+
+        let issues = check_weeks(&weeks, None);
+
+        assert_eq!(3, issues.len());
+        assert_eq!(
+            RegistryLocation::Day { week: 1, day: 0 },
+            issues[0].location
+        );
+        assert!(matches!(
+            issues[0].problem,
+            IntegrityProblem::UnorderedDay { .. }
+        ));
+        assert_eq!(
+            IntegrityProblem::WorkedSecondsMismatch {
+                cached: TimeDelta::hours(3).num_seconds(),
+                counted: TimeDelta::hours(8).num_seconds(),
+            },
+            issues[1].problem
+        );
+        assert_eq!(
+            RegistryLocation::Day { week: 1, day: 2 },
+            issues[2].location
+        );
+        assert_eq!(IntegrityProblem::EmptyDay, issues[2].problem);
+
+        assert_eq!(2, repair_weeks(&mut weeks, None));
+        let remaining = check_weeks(&weeks, None);
+        assert_eq!(1, remaining.len());
+        assert!(!remaining[0].is_repairable());
+    }
+
+    #[test]
+    fn counts_are_checked_against_the_recorded_clock_policy() {
+        let rounded = DayPolicy {
+            clock_time: ClockTimePolicy {
+                rounding_step: TimeDelta::minutes(15),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let journey = WorkJourney::new(
+            Utc.with_ymd_and_hms(2024, 7, 10, 8, 7, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 10, 16, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let mut week = WorkWeek::new();
+        week.append_day(
+            &WorkDay::new(&[journey], &rounded).unwrap(),
+            &WeekPolicy::default(),
+        );
+        let mut legacy_week = initialize_fixed_week(2024, 7, &[11], 8);
+        let mut legacy_day = serde_json::to_value(&legacy_week.workdays[0]).unwrap();
+        legacy_day.as_object_mut().unwrap().remove("clock_time");
+        legacy_day["worked_seconds"] = TimeDelta::hours(3).num_seconds().into();
+        legacy_week.workdays[0] = serde_json::from_value(legacy_day).unwrap();
+
+        assert_eq!(
+            TimeDelta::hours(8).num_seconds(),
+            week.workdays[0].worked_hours()
+        );
+        assert!(check_weeks(std::slice::from_ref(&week), None).is_empty());
+
+        let mut weeks = [week, legacy_week];
+        let issues = check_weeks(&weeks, None);
+        assert_eq!(1, issues.len());
+        assert_eq!(
+            IntegrityProblem::WorkedSecondsMismatch {
+                cached: TimeDelta::hours(3).num_seconds(),
+                counted: TimeDelta::hours(8).num_seconds(),
+            },
+            issues[0].problem
+        );
+        assert_eq!(1, repair_weeks(&mut weeks, None));
+        assert!(check_weeks(&weeks, None).is_empty());
+    }
+
+    #[test]
+    fn current_day_journeys_must_not_overlap() {
+        let at = |hour| Utc.with_ymd_and_hms(2024, 7, 10, hour, 0, 0).unwrap();
+        let journeys = [
+            WorkJourney::new(at(8), at(12)).unwrap(),
+            WorkJourney::new(at(11), at(14)).unwrap(),
+        ];
+
+        assert!(check_current_day(&[]).is_empty());
+        assert_eq!(
+            IntegrityProblem::InvalidJourneys(WorkDayError::OverlappingJourneys(1)),
+            check_current_day(&journeys)[0].problem
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt::Display;
+
+use chrono::{NaiveDate, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::delta_hours::{format_seconds, DeltaHours};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LedgerEntryKind {
+    /// Balance carried over from a previous system.
+    OpeningBalance,
+    /// Manual change, such as a payout or a bank reset.
+    Adjustment,
+    /// Reset of the balance when a period is closed.
+    Settlement,
+}
+
+/// Dated change to the hour balance that did not come from worked days.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub date: NaiveDate,
+    pub kind: LedgerEntryKind,
+    /// Hours added to the balance, negative when they are owed.
+    #[serde(with = "crate::policy::seconds")]
+    pub credit: TimeDelta,
+    pub note: String,
+}
+
+impl LedgerEntry {
+    pub fn new(
+        date: NaiveDate,
+        kind: LedgerEntryKind,
+        credit: TimeDelta,
+        note: String,
+    ) -> LedgerEntry {
+        LedgerEntry {
+            date,
+            kind,
+            credit,
+            note,
+        }
+    }
+
+    pub fn delta(&self) -> DeltaHours {
+        DeltaHours::new(-self.credit.num_seconds())
+    }
+}
+
+impl Display for LedgerEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            LedgerEntryKind::OpeningBalance => "Opening balance",
+            LedgerEntryKind::Adjustment => "Adjustment",
+            LedgerEntryKind::Settlement => "Settlement",
+        };
+        write!(
+            f,
+            "{} {}: {}{}",
+            self.date,
+            kind,
+            if self.credit < TimeDelta::zero() {
+                "-"
+            } else {
+                "+"
+            },
+            format_seconds(self.credit.num_seconds().abs())
+        )?;
+        if !self.note.is_empty() {
+            write!(f, " ({})", self.note)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SettlementKind {
+    /// Credit paid out to the employee.
+    Payout,
+    /// Debt deducted from the employee.
+    Deduction,
+    /// Nothing to settle.
+    Balanced,
+}
+
+/// Balance of a closed period and how it was settled.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PeriodSettlement {
+    /// Day after the previous closing, `None` for the first period.
+    pub period_start: Option<NaiveDate>,
+    pub closed_on: NaiveDate,
+    /// Balance at the closing, negative when hours were owed.
+    #[serde(with = "crate::policy::seconds")]
+    pub balance: TimeDelta,
+    pub kind: SettlementKind,
+    pub note: String,
+}
+
+impl PeriodSettlement {
+    pub fn new(
+        period_start: Option<NaiveDate>,
+        closed_on: NaiveDate,
+        balance: TimeDelta,
+        note: String,
+    ) -> PeriodSettlement {
+        let kind = if balance > TimeDelta::zero() {
+            SettlementKind::Payout
+        } else if balance < TimeDelta::zero() {
+            SettlementKind::Deduction
+        } else {
+            SettlementKind::Balanced
+        };
+
+        PeriodSettlement {
+            period_start,
+            closed_on,
+            balance,
+            kind,
+            note,
+        }
+    }
+
+    /// Ledger entry that brings the balance back to zero.
+    pub fn reset_entry(&self) -> LedgerEntry {
+        LedgerEntry::new(
+            self.closed_on,
+            LedgerEntryKind::Settlement,
+            -self.balance,
+            self.note.clone(),
+        )
+    }
+}
+
+impl Display for PeriodSettlement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.period_start {
+            Some(period_start) => write!(f, "{} to {}", period_start, self.closed_on)?,
+            None => write!(f, "Until {}", self.closed_on)?,
+        }
+        let hours = format_seconds(self.balance.num_seconds().abs());
+        match self.kind {
+            SettlementKind::Payout => write!(f, ": paid out {}", hours)?,
+            SettlementKind::Deduction => write!(f, ": deducted {}", hours)?,
+            SettlementKind::Balanced => write!(f, ": nothing to settle")?,
+        }
+        if !self.note.is_empty() {
+            write!(f, " ({})", self.note)?;
+        }
+        Ok(())
+    }
+}
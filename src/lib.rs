@@ -1,6 +1,16 @@
+pub mod clock;
 pub mod clockedin_service;
+pub mod compliance_report;
 pub mod delta_hours;
+pub mod integrity;
+pub mod ledger;
 pub mod long_term_registry;
+pub mod policy;
+pub mod premium;
+pub mod punch_pairing;
+pub mod recommendations;
+pub mod recompute;
+pub mod violations;
 pub mod work_days;
 pub mod work_journey;
 pub mod work_week;
@@ -1,9 +1,15 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use std::num::TryFromIntError;
 use thiserror::Error;
 
-use crate::{delta_hours::DeltaHours, work_week::WorkWeek};
+use crate::{
+    delta_hours::DeltaHours,
+    ledger::{LedgerEntry, LedgerEntryKind, PeriodSettlement},
+    policy::PolicySchedule,
+    premium::PremiumBuckets,
+    work_week::{average_weekly_hours, WorkWeek},
+};
 
 #[derive(Error, Debug)]
 pub enum LongTermRegistryError {
@@ -11,18 +17,30 @@ pub enum LongTermRegistryError {
     EmptyHistory,
     #[error("Error during int conversion {0}")]
     IntConversionError(TryFromIntError),
+    #[error("Period until {0} is already closed.")]
+    PeriodAlreadyClosed(NaiveDate),
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct LongTermRegistry {
     pub history: Vec<WorkWeek>,
+    #[serde(default)]
+    ledger: Vec<LedgerEntry>,
+    #[serde(default)]
+    settlements: Vec<PeriodSettlement>,
 }
 
 impl LongTermRegistry {
     pub fn new() -> LongTermRegistry {
         let history = Vec::new();
+        let ledger = Vec::new();
+        let settlements = Vec::new();
 
-        LongTermRegistry { history }
+        LongTermRegistry {
+            history,
+            ledger,
+            settlements,
+        }
     }
 
     pub fn worked_hours(&self) -> i64 {
@@ -47,9 +65,127 @@ impl LongTermRegistry {
                 .map_err(LongTermRegistryError::IntConversionError)?
         }
 
+        current_delta += self.ledger_delta();
+
         Ok(current_delta)
     }
 
+    /// Delta of the ledger entries alone.
+    pub fn ledger_delta(&self) -> DeltaHours {
+        self.ledger
+            .iter()
+            .fold(DeltaHours::default(), |mut acc, entry| {
+                acc += entry.delta();
+                acc
+            })
+    }
+
+    /// Records an entry, keeping the ledger ordered by date. An opening
+    /// balance replaces the previous one. Closed periods can't be changed.
+    pub fn add_ledger_entry(&mut self, entry: LedgerEntry) -> Result<(), LongTermRegistryError> {
+        if let Some(closed_on) = self.closed_until() {
+            let replaces_closed_entry = entry.kind == LedgerEntryKind::OpeningBalance
+                && self.ledger.iter().any(|item| {
+                    item.kind == LedgerEntryKind::OpeningBalance && item.date <= closed_on
+                });
+            if entry.date <= closed_on || replaces_closed_entry {
+                return Err(LongTermRegistryError::PeriodAlreadyClosed(closed_on));
+            }
+        }
+
+        if entry.kind == LedgerEntryKind::OpeningBalance {
+            self.ledger
+                .retain(|item| item.kind != LedgerEntryKind::OpeningBalance);
+        }
+        let index = self.ledger.partition_point(|item| item.date <= entry.date);
+        self.ledger.insert(index, entry);
+        Ok(())
+    }
+
+    pub fn get_ledger(&self) -> &[LedgerEntry] {
+        &self.ledger
+    }
+
+    /// Delta of the days and ledger entries up to `date`, inclusive, counting
+    /// the `pending_weeks` not yet in the history.
+    pub fn delta_until(&self, date: NaiveDate, pending_weeks: &[WorkWeek]) -> DeltaHours {
+        let days_delta: i64 = self
+            .history
+            .iter()
+            .chain(pending_weeks)
+            .flat_map(|week| week.workdays.iter())
+            .filter(|day| day.date().is_some_and(|day_date| day_date <= date))
+            .map(|day| day.expected_hours() - day.worked_hours())
+            .sum();
+
+        self.ledger.iter().filter(|entry| entry.date <= date).fold(
+            DeltaHours::new(days_delta),
+            |mut acc, entry| {
+                acc += entry.delta();
+                acc
+            },
+        )
+    }
+
+    /// Closes the period ending on `closed_on`: snapshots the balance, keeps
+    /// the settlement and resets the running delta with a ledger entry.
+    pub fn close_period(
+        &mut self,
+        closed_on: NaiveDate,
+        note: String,
+        pending_weeks: &[WorkWeek],
+    ) -> Result<PeriodSettlement, LongTermRegistryError> {
+        let period_start = match self.settlements.last() {
+            Some(last) if last.closed_on >= closed_on => {
+                return Err(LongTermRegistryError::PeriodAlreadyClosed(last.closed_on))
+            }
+            Some(last) => last.closed_on.succ_opt(),
+            None => None,
+        };
+
+        let balance = -self.delta_until(closed_on, pending_weeks).num_seconds();
+        let settlement =
+            PeriodSettlement::new(period_start, closed_on, TimeDelta::seconds(balance), note);
+        self.add_ledger_entry(settlement.reset_entry())?;
+        self.settlements.push(settlement.clone());
+
+        Ok(settlement)
+    }
+
+    pub fn get_settlements(&self) -> &[PeriodSettlement] {
+        &self.settlements
+    }
+
+    /// Last day of the last closed period, if any period was closed.
+    pub fn closed_until(&self) -> Option<NaiveDate> {
+        self.settlements
+            .last()
+            .map(|settlement| settlement.closed_on)
+    }
+
+    pub fn premium_buckets_for_month(
+        &self,
+        year: i32,
+        month: u32,
+        policy: &PolicySchedule,
+    ) -> PremiumBuckets {
+        self.history
+            .iter()
+            .flat_map(|week| week.workdays.iter())
+            .filter_map(|day| Some((day, day.date()?)))
+            .filter(|(_, date)| date.year() == year && date.month() == month)
+            .fold(PremiumBuckets::default(), |mut acc, (item, date)| {
+                acc += item.premium_buckets(policy.policy_at(date));
+                acc
+            })
+    }
+
+    /// Average weekly hours over the last `reference_period_weeks` weeks of
+    /// history.
+    pub fn average_weekly_hours(&self, reference_period_weeks: usize) -> Option<i64> {
+        average_weekly_hours(self.history.iter().rev().take(reference_period_weeks))
+    }
+
     pub fn last_clock_out_last_week(&self) -> Option<DateTime<Utc>> {
         self.history
             .last()
@@ -65,11 +201,21 @@ impl Default for LongTermRegistry {
 
 #[cfg(test)]
 mod tests {
-    use chrono::TimeDelta;
+    use chrono::{NaiveDate, TimeDelta, TimeZone, Utc};
 
-    use crate::{delta_hours::DeltaHours, work_week::tests::intialize_mock_week};
+    use crate::{
+        delta_hours::DeltaHours,
+        ledger::{LedgerEntry, LedgerEntryKind, SettlementKind},
+        policy::{DayPolicy, PolicySchedule, WeekPolicy},
+        work_days::WorkDay,
+        work_journey::WorkJourney,
+        work_week::{
+            tests::{initialize_fixed_week, intialize_mock_week},
+            WorkWeek,
+        },
+    };
 
-    use super::LongTermRegistry;
+    use super::{LongTermRegistry, LongTermRegistryError};
 
     #[test]
     fn basic_long_term_registry_initialization() {
@@ -96,6 +242,149 @@ mod tests {
         )
     }
 
+    #[test]
+    fn average_weekly_hours_over_reference_period() {
+        let mut long_term_registry = LongTermRegistry::new();
+        assert_eq!(None, long_term_registry.average_weekly_hours(17));
+
+        long_term_registry.history = vec![
+            initialize_fixed_week(2024, 7, &[1, 2, 3, 4, 5], 6),
+            initialize_fixed_week(2024, 7, &[8, 9, 10, 11, 12], 8),
+            initialize_fixed_week(2024, 7, &[15, 16, 17, 18], 8),
+        ];
+
+        assert_eq!(
+            Some(TimeDelta::hours(36).num_seconds()),
+            long_term_registry.average_weekly_hours(2)
+        );
+        assert_eq!(
+            Some(TimeDelta::hours(34).num_seconds()),
+            long_term_registry.average_weekly_hours(17)
+        );
+    }
+
+    #[test]
+    fn ledger_entries_feed_the_worked_delta() {
+        let mut long_term_registry = initialize_mock_long_term_registry();
+        let date = |day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+        long_term_registry
+            .add_ledger_entry(LedgerEntry::new(
+                date(30),
+                LedgerEntryKind::Adjustment,
+                TimeDelta::hours(-4),
+                "Payout".to_string(),
+            ))
+            .unwrap();
+        long_term_registry
+            .add_ledger_entry(LedgerEntry::new(
+                date(1),
+                LedgerEntryKind::OpeningBalance,
+                TimeDelta::hours(10),
+                String::new(),
+            ))
+            .unwrap();
+        long_term_registry
+            .add_ledger_entry(LedgerEntry::new(
+                date(1),
+                LedgerEntryKind::OpeningBalance,
+                TimeDelta::hours(30),
+                "Previous system".to_string(),
+            ))
+            .unwrap();
+
+        assert_eq!(2, long_term_registry.get_ledger().len());
+        assert_eq!(
+            LedgerEntryKind::OpeningBalance,
+            long_term_registry.get_ledger()[0].kind
+        );
+        assert_eq!(
+            TimeDelta::hours(-26).num_seconds(),
+            long_term_registry.ledger_delta().num_seconds()
+        );
+        assert_eq!(
+            TimeDelta::hours(-6).num_seconds(),
+            long_term_registry.worked_delta().unwrap().num_seconds()
+        );
+    }
+
+    #[test]
+    fn closing_periods_settles_and_resets_the_balance() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 7, day).unwrap();
+        let mut long_term_registry = LongTermRegistry::new();
+        long_term_registry.history = vec![
+            initialize_fixed_week(2024, 7, &[1, 2, 3, 4, 5], 9),
+            initialize_fixed_week(2024, 7, &[8, 9, 10], 6),
+        ];
+        let pending_week = initialize_fixed_week(2024, 7, &[15], 7);
+
+        let first = long_term_registry
+            .close_period(date(5), "First semester".to_string(), &[])
+            .unwrap();
+        assert_eq!(SettlementKind::Payout, first.kind);
+        assert_eq!(TimeDelta::hours(5), first.balance);
+        assert_eq!(
+            TimeDelta::hours(6).num_seconds(),
+            long_term_registry.worked_delta().unwrap().num_seconds()
+        );
+
+        let second = long_term_registry
+            .close_period(date(15), String::new(), std::slice::from_ref(&pending_week))
+            .unwrap();
+        assert_eq!(SettlementKind::Deduction, second.kind);
+        assert_eq!(TimeDelta::hours(-7), second.balance);
+        assert_eq!(Some(date(6)), second.period_start);
+        assert_eq!(
+            TimeDelta::hours(-1).num_seconds(),
+            long_term_registry.worked_delta().unwrap().num_seconds()
+        );
+
+        assert!(matches!(
+            long_term_registry.close_period(date(12), String::new(), &[]),
+            Err(LongTermRegistryError::PeriodAlreadyClosed(_))
+        ));
+        assert!(matches!(
+            long_term_registry.add_ledger_entry(LedgerEntry::new(
+                date(10),
+                LedgerEntryKind::Adjustment,
+                TimeDelta::hours(1),
+                String::new(),
+            )),
+            Err(LongTermRegistryError::PeriodAlreadyClosed(_))
+        ));
+        assert_eq!(2, long_term_registry.get_settlements().len());
+        assert_eq!(Some(date(15)), long_term_registry.closed_until());
+    }
+
+    #[test]
+    fn premium_buckets_for_month_math() {
+        let mut long_term_registry = LongTermRegistry::new();
+        let mut week = WorkWeek::new();
+        for day in [30, 31] {
+            let start = Utc.with_ymd_and_hms(2024, 7, day, 8, 0, 0).unwrap();
+            let journey = WorkJourney::new(start, start + TimeDelta::hours(9)).unwrap();
+            week.append_day(
+                &WorkDay::new(&[journey], &DayPolicy::default()).unwrap(),
+                &WeekPolicy::default(),
+            );
+        }
+        let start = Utc.with_ymd_and_hms(2024, 8, 1, 8, 0, 0).unwrap();
+        let journey = WorkJourney::new(start, start + TimeDelta::hours(9)).unwrap();
+        week.append_day(
+            &WorkDay::new(&[journey], &DayPolicy::default()).unwrap(),
+            &WeekPolicy::default(),
+        );
+        long_term_registry.history.push(week);
+
+        let july =
+            long_term_registry.premium_buckets_for_month(2024, 7, &PolicySchedule::default());
+        let august =
+            long_term_registry.premium_buckets_for_month(2024, 8, &PolicySchedule::default());
+
+        assert_eq!(TimeDelta::hours(2 * 8).num_seconds(), july.regular);
+        assert_eq!(TimeDelta::hours(2).num_seconds(), july.overtime);
+        assert_eq!(TimeDelta::hours(9).num_seconds(), august.worked_seconds());
+    }
+
     fn initialize_mock_long_term_registry() -> LongTermRegistry {
         let mut long_term_registry = LongTermRegistry::new();
         let week1 = intialize_mock_week();
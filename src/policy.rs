@@ -0,0 +1,580 @@
+use chrono::{DateTime, Datelike, DurationRound, NaiveDate, NaiveTime, TimeDelta, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clockedin_service::{
+        EXPECTED_OVERTIME_WORK_JOURNEY_TIME_DELTA, EXPECTED_WORK_JOURNEY_TIME_DELTA,
+    },
+    work_days::{MAX_HOURS_PER_JOURNEY, MAX_JOURNEYS_PER_DAY},
+    work_journey::WorkJourney,
+    work_week::{WorkWeek, MIN_INTER_DAY_REST_TIME_DELTA},
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayPolicy {
+    #[serde(with = "seconds")]
+    pub expected_hours: TimeDelta,
+    #[serde(with = "seconds")]
+    pub min_hours: TimeDelta,
+    #[serde(with = "seconds")]
+    pub max_hours: TimeDelta,
+    #[serde(with = "seconds")]
+    pub max_journey_hours: TimeDelta,
+    pub max_journeys: usize,
+    /// Days worked above this length need `short_day_break`.
+    #[serde(with = "seconds")]
+    pub short_day_threshold: TimeDelta,
+    #[serde(with = "seconds")]
+    pub short_day_break: TimeDelta,
+    /// Days worked above this length need `long_day_break` instead.
+    #[serde(with = "seconds")]
+    pub long_day_threshold: TimeDelta,
+    #[serde(with = "seconds")]
+    pub long_day_break: TimeDelta,
+    #[serde(with = "seconds")]
+    pub max_break: TimeDelta,
+    /// Breaks shorter than this are not counted as rest.
+    #[serde(with = "seconds")]
+    pub min_counted_break: TimeDelta,
+    pub clock_time: ClockTimePolicy,
+}
+
+impl Default for DayPolicy {
+    fn default() -> Self {
+        Self {
+            expected_hours: EXPECTED_WORK_JOURNEY_TIME_DELTA,
+            min_hours: TimeDelta::hours(6),
+            max_hours: EXPECTED_WORK_JOURNEY_TIME_DELTA + EXPECTED_OVERTIME_WORK_JOURNEY_TIME_DELTA,
+            max_journey_hours: MAX_HOURS_PER_JOURNEY,
+            max_journeys: MAX_JOURNEYS_PER_DAY,
+            short_day_threshold: TimeDelta::hours(4),
+            short_day_break: TimeDelta::minutes(15),
+            long_day_threshold: TimeDelta::hours(6),
+            long_day_break: TimeDelta::hours(1),
+            max_break: TimeDelta::hours(2),
+            min_counted_break: TimeDelta::minutes(10),
+            clock_time: ClockTimePolicy::default(),
+        }
+    }
+}
+
+/// How punches are turned into counted time. Only the counted time is
+/// affected; the journeys keep their raw timestamps.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockTimePolicy {
+    /// Punches are rounded to the nearest multiple of this step. Zero disables
+    /// rounding.
+    #[serde(with = "seconds")]
+    pub rounding_step: TimeDelta,
+    /// Largest deviation of a single punch from its scheduled time that is
+    /// not counted.
+    #[serde(with = "seconds")]
+    pub punch_tolerance: TimeDelta,
+    /// Largest sum of the punch deviations in a day that is not counted.
+    #[serde(with = "seconds")]
+    pub daily_tolerance: TimeDelta,
+    /// Scheduled punch times of a regular day, in order. The tolerances only
+    /// apply to days with one punch per scheduled time.
+    pub scheduled_punches: Vec<NaiveTime>,
+}
+
+impl ClockTimePolicy {
+    pub fn round(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        if self.rounding_step <= TimeDelta::zero() {
+            return time;
+        }
+        time.duration_round(self.rounding_step).unwrap_or(time)
+    }
+
+    /// Worked time of the journeys after rounding. A day whose punches each
+    /// stay within the punch tolerance of the schedule, and whose deviations
+    /// add up to no more than the daily tolerance, counts as scheduled.
+    pub fn counted_hours(&self, journeys: &[WorkJourney]) -> TimeDelta {
+        if let Some(scheduled_hours) = self.scheduled_hours_within_tolerance(journeys) {
+            return scheduled_hours;
+        }
+
+        journeys.iter().fold(TimeDelta::zero(), |acc, journey| {
+            let rounded =
+                self.round(journey.get_ending_time()) - self.round(journey.get_starting_time());
+            acc + rounded.max(TimeDelta::zero())
+        })
+    }
+
+    fn scheduled_hours_within_tolerance(&self, journeys: &[WorkJourney]) -> Option<TimeDelta> {
+        if self.scheduled_punches.is_empty() || self.scheduled_punches.len() != journeys.len() * 2 {
+            return None;
+        }
+
+        let punches = journeys
+            .iter()
+            .flat_map(|journey| [journey.get_starting_time(), journey.get_ending_time()]);
+        let mut scheduled_times = Vec::with_capacity(self.scheduled_punches.len());
+        let mut total_deviation = TimeDelta::zero();
+        for (punch, scheduled_punch) in punches.zip(&self.scheduled_punches) {
+            let scheduled_time = punch.date_naive().and_time(*scheduled_punch).and_utc();
+            let deviation = (punch - scheduled_time).abs();
+            if deviation > self.punch_tolerance {
+                return None;
+            }
+            total_deviation += deviation;
+            scheduled_times.push(scheduled_time);
+        }
+        if total_deviation > self.daily_tolerance {
+            return None;
+        }
+
+        Some(
+            scheduled_times
+                .chunks(2)
+                .map(|pair| (pair[1] - pair[0]).max(TimeDelta::zero()))
+                .sum(),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeekPolicy {
+    #[serde(with = "seconds")]
+    pub max_weekly_hours: TimeDelta,
+    #[serde(with = "seconds")]
+    pub min_inter_day_rest: TimeDelta,
+    /// Limit on the average weekly hours over `reference_period_weeks`, checked
+    /// once the history covers a full reference period. Zero disables it.
+    #[serde(with = "seconds")]
+    pub max_average_weekly_hours: TimeDelta,
+    pub reference_period_weeks: usize,
+    #[serde(with = "seconds")]
+    pub min_weekly_rest: TimeDelta,
+    pub max_consecutive_workdays: usize,
+    pub max_consecutive_sundays: usize,
+}
+
+impl Default for WeekPolicy {
+    fn default() -> Self {
+        Self {
+            max_weekly_hours: TimeDelta::hours(44),
+            min_inter_day_rest: MIN_INTER_DAY_REST_TIME_DELTA,
+            max_average_weekly_hours: TimeDelta::zero(),
+            reference_period_weeks: 17,
+            min_weekly_rest: TimeDelta::hours(24),
+            max_consecutive_workdays: 6,
+            max_consecutive_sundays: 6,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PremiumPolicy {
+    pub regular_multiplier: f64,
+    pub overtime_multiplier: f64,
+    pub sunday_holiday_multiplier: f64,
+    pub night_multiplier: f64,
+    pub night_start: NaiveTime,
+    pub night_end: NaiveTime,
+    #[serde(with = "seconds")]
+    pub night_hour: TimeDelta,
+    pub holidays: Vec<NaiveDate>,
+    /// Time above the expected daily hours counts as overtime.
+    pub daily_overtime: bool,
+    /// Time above this in a week counts as overtime. Zero disables it.
+    #[serde(with = "seconds")]
+    pub weekly_overtime_threshold: TimeDelta,
+}
+
+impl PremiumPolicy {
+    pub fn is_sunday_or_holiday(&self, date: NaiveDate) -> bool {
+        date.weekday() == Weekday::Sun || self.holidays.contains(&date)
+    }
+
+    pub fn is_night(&self, time: NaiveTime) -> bool {
+        if self.night_start > self.night_end {
+            time >= self.night_start || time < self.night_end
+        } else {
+            time >= self.night_start && time < self.night_end
+        }
+    }
+}
+
+impl Default for PremiumPolicy {
+    fn default() -> Self {
+        Self {
+            regular_multiplier: 1.0,
+            overtime_multiplier: 1.5,
+            sunday_holiday_multiplier: 2.0,
+            night_multiplier: 1.2,
+            night_start: NaiveTime::from_hms_opt(22, 0, 0).expect("Valid constant time."),
+            night_end: NaiveTime::from_hms_opt(5, 0, 0).expect("Valid constant time."),
+            night_hour: TimeDelta::minutes(52) + TimeDelta::seconds(30),
+            holidays: Vec::new(),
+            daily_overtime: true,
+            weekly_overtime_threshold: TimeDelta::zero(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkPolicy {
+    pub day: DayPolicy,
+    pub week: WeekPolicy,
+    pub premium: PremiumPolicy,
+}
+
+impl WorkPolicy {
+    /// Daily time after which worked hours count as overtime.
+    pub fn daily_overtime_threshold(&self) -> TimeDelta {
+        if self.premium.daily_overtime {
+            self.day.expected_hours
+        } else {
+            TimeDelta::days(1)
+        }
+    }
+}
+
+/// A policy in force from `effective_from` until the next version.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PolicyVersion {
+    pub effective_from: NaiveDate,
+    pub policy: WorkPolicy,
+}
+
+/// Policies over time, so each date is evaluated against the rules in force
+/// then.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolicySchedule {
+    /// Policy in force before the first version.
+    #[serde(rename = "policy", default)]
+    pub base: WorkPolicy,
+    #[serde(rename = "policy_versions", default)]
+    versions: Vec<PolicyVersion>,
+}
+
+impl PolicySchedule {
+    pub fn new(base: WorkPolicy) -> PolicySchedule {
+        PolicySchedule {
+            base,
+            versions: Vec::new(),
+        }
+    }
+
+    pub fn policy_at(&self, date: NaiveDate) -> &WorkPolicy {
+        self.versions
+            .iter()
+            .rev()
+            .find(|version| version.effective_from <= date)
+            .map_or(&self.base, |version| &version.policy)
+    }
+
+    /// Policy in force on the last day of the week, the base for an empty week.
+    pub fn policy_for_week(&self, week: &WorkWeek) -> &WorkPolicy {
+        week.last_clock_out_last_day_in_week()
+            .map_or(&self.base, |last_clock_out| {
+                self.policy_at(last_clock_out.date_naive())
+            })
+    }
+
+    /// Adds a version, replacing the one with the same effective date.
+    pub fn add_version(&mut self, effective_from: NaiveDate, policy: WorkPolicy) {
+        match self
+            .versions
+            .binary_search_by_key(&effective_from, |version| version.effective_from)
+        {
+            Ok(index) => self.versions[index].policy = policy,
+            Err(index) => self.versions.insert(
+                index,
+                PolicyVersion {
+                    effective_from,
+                    policy,
+                },
+            ),
+        }
+    }
+
+    pub fn get_versions(&self) -> &[PolicyVersion] {
+        &self.versions
+    }
+}
+
+/// Shipped policies for common labor regimes, selectable by name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PolicyPreset {
+    /// Brazilian CLT: 8h days, 44h weeks, 2h overtime cap, 1h intra-day and
+    /// 11h inter-day rest.
+    Clt,
+    /// EU Working Time Directive: 48h average weeks over 17 weeks, 11h daily
+    /// and 24h weekly rest.
+    EuWorkingTimeDirective,
+    /// US FLSA: no daily limits, overtime after 40h in a week.
+    UsFlsa,
+}
+
+impl PolicyPreset {
+    pub const ALL: [PolicyPreset; 3] = [
+        PolicyPreset::Clt,
+        PolicyPreset::EuWorkingTimeDirective,
+        PolicyPreset::UsFlsa,
+    ];
+
+    pub fn from_name(name: &str) -> Option<PolicyPreset> {
+        PolicyPreset::ALL
+            .into_iter()
+            .find(|preset| preset.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PolicyPreset::Clt => "clt",
+            PolicyPreset::EuWorkingTimeDirective => "eu_wtd",
+            PolicyPreset::UsFlsa => "us_flsa",
+        }
+    }
+
+    pub fn policy(&self) -> WorkPolicy {
+        match self {
+            PolicyPreset::Clt => WorkPolicy {
+                day: DayPolicy {
+                    expected_hours: TimeDelta::hours(8),
+                    max_hours: TimeDelta::hours(10),
+                    long_day_threshold: TimeDelta::hours(6),
+                    long_day_break: TimeDelta::hours(1),
+                    ..Default::default()
+                },
+                week: WeekPolicy {
+                    max_weekly_hours: TimeDelta::hours(44),
+                    min_inter_day_rest: TimeDelta::hours(11),
+                    ..Default::default()
+                },
+                premium: PremiumPolicy::default(),
+            },
+            PolicyPreset::EuWorkingTimeDirective => WorkPolicy {
+                day: DayPolicy {
+                    min_hours: TimeDelta::zero(),
+                    max_hours: TimeDelta::hours(13),
+                    max_journeys: usize::MAX,
+                    short_day_threshold: TimeDelta::hours(6),
+                    short_day_break: TimeDelta::minutes(15),
+                    long_day_threshold: TimeDelta::hours(6),
+                    long_day_break: TimeDelta::minutes(15),
+                    max_break: TimeDelta::days(1),
+                    ..Default::default()
+                },
+                week: WeekPolicy {
+                    max_weekly_hours: TimeDelta::weeks(1),
+                    min_inter_day_rest: TimeDelta::hours(11),
+                    max_average_weekly_hours: TimeDelta::hours(48),
+                    reference_period_weeks: 17,
+                    min_weekly_rest: TimeDelta::hours(24),
+                    max_consecutive_workdays: 6,
+                    max_consecutive_sundays: usize::MAX,
+                },
+                premium: PremiumPolicy {
+                    daily_overtime: false,
+                    ..Default::default()
+                },
+            },
+            PolicyPreset::UsFlsa => WorkPolicy {
+                day: DayPolicy {
+                    min_hours: TimeDelta::zero(),
+                    max_hours: TimeDelta::days(1),
+                    max_journey_hours: TimeDelta::days(1),
+                    max_journeys: usize::MAX,
+                    short_day_break: TimeDelta::zero(),
+                    long_day_break: TimeDelta::zero(),
+                    max_break: TimeDelta::days(1),
+                    ..Default::default()
+                },
+                week: WeekPolicy {
+                    max_weekly_hours: TimeDelta::weeks(1),
+                    min_inter_day_rest: TimeDelta::zero(),
+                    min_weekly_rest: TimeDelta::zero(),
+                    max_consecutive_workdays: usize::MAX,
+                    max_consecutive_sundays: usize::MAX,
+                    ..Default::default()
+                },
+                premium: PremiumPolicy {
+                    sunday_holiday_multiplier: 1.0,
+                    night_multiplier: 1.0,
+                    daily_overtime: false,
+                    weekly_overtime_threshold: TimeDelta::hours(40),
+                    ..Default::default()
+                },
+            },
+        }
+    }
+}
+
+pub mod seconds {
+    use chrono::TimeDelta;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(delta: &TimeDelta, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(delta.num_seconds())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeDelta, D::Error> {
+        Ok(TimeDelta::seconds(i64::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta, TimeZone, Utc};
+
+    use crate::{
+        violations::ViolationKind, work_days::WorkDay, work_journey::WorkJourney,
+        work_week::WorkWeek,
+    };
+
+    use super::{DayPolicy, PolicyPreset, PolicySchedule, WorkPolicy};
+
+    /// Builds a day on July 2024 from `(start hour, length in minutes)` pairs.
+    fn fixed_day(day: u32, journeys: &[(u32, i64)], policy: &WorkPolicy) -> WorkDay {
+        let journeys: Vec<WorkJourney> = journeys
+            .iter()
+            .map(|(hour, length)| {
+                let start = Utc.with_ymd_and_hms(2024, 7, day, *hour, 0, 0).unwrap();
+                WorkJourney::new(start, start + TimeDelta::minutes(*length)).unwrap()
+            })
+            .collect();
+        WorkDay::new(&journeys, &policy.day).unwrap()
+    }
+
+    fn fixed_week(days: &[u32], journeys: &[(u32, i64)], policy: &WorkPolicy) -> WorkWeek {
+        let mut week = WorkWeek::new();
+        for day in days {
+            week.append_day(&fixed_day(*day, journeys, policy), &policy.week);
+        }
+        week.evaluate_weekly_rules(&[], &policy.week);
+        week
+    }
+
+    #[test]
+    fn presets_are_selectable_by_name() {
+        for preset in PolicyPreset::ALL {
+            assert_eq!(Some(preset), PolicyPreset::from_name(preset.name()));
+        }
+        assert_eq!(None, PolicyPreset::from_name("unknown"));
+        assert_eq!(WorkPolicy::default(), PolicyPreset::Clt.policy());
+    }
+
+    #[test]
+    fn schedule_selects_the_version_in_force() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 7, day).unwrap();
+        let part_time = WorkPolicy {
+            day: DayPolicy {
+                expected_hours: TimeDelta::hours(6),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut schedule = PolicySchedule::new(part_time.clone());
+        schedule.add_version(date(15), PolicyPreset::UsFlsa.policy());
+        schedule.add_version(date(10), WorkPolicy::default());
+
+        assert_eq!(&part_time, schedule.policy_at(date(9)));
+        assert_eq!(&WorkPolicy::default(), schedule.policy_at(date(10)));
+        assert_eq!(&PolicyPreset::UsFlsa.policy(), schedule.policy_at(date(20)));
+
+        let legacy: PolicySchedule =
+            serde_json::from_str(r#"{"policy": {"day": {"expected_hours": 21600}}}"#).unwrap();
+        assert_eq!(
+            TimeDelta::hours(6),
+            legacy.policy_at(date(9)).day.expected_hours
+        );
+    }
+
+    #[test]
+    fn clt_preset_rules() {
+        let policy = PolicyPreset::Clt.policy();
+
+        let long_day = fixed_day(8, &[(8, 300), (14, 360)], &policy);
+        let no_break_day = fixed_day(8, &[(8, 240), (12, 180)], &policy);
+        let week = fixed_week(&[8, 9, 10, 11, 12], &[(8, 240), (13, 300)], &policy);
+
+        assert!(long_day.has_violation(ViolationKind::ExceededMaxHours));
+        assert!(no_break_day.has_violation(ViolationKind::ViolatedInterJourneyRest));
+        assert!(week.has_violation(ViolationKind::ExceededMaxWeeklyHours));
+
+        let mut short_rest_week = WorkWeek::new();
+        short_rest_week.append_day(&fixed_day(8, &[(12, 600)], &policy), &policy.week);
+        short_rest_week.append_day(&fixed_day(9, &[(8, 240)], &policy), &policy.week);
+        assert!(short_rest_week.has_violation(ViolationKind::InterDayRest));
+    }
+
+    #[test]
+    fn eu_working_time_directive_preset_rules() {
+        let mut policy = PolicyPreset::EuWorkingTimeDirective.policy();
+        policy.week.reference_period_weeks = 3;
+
+        let twelve_hours = fixed_day(8, &[(6, 360), (12, 360)], &policy);
+        let fourteen_hours = fixed_day(8, &[(5, 360), (11, 360), (17, 120)], &policy);
+        let forty_five_hours = fixed_week(&[8, 9, 10, 11, 12], &[(8, 300), (13, 240)], &policy);
+        let mut fifty_hours = WorkWeek::new();
+        for day in [15, 16, 17, 18, 19] {
+            fifty_hours.append_day(
+                &fixed_day(day, &[(7, 300), (13, 300)], &policy),
+                &policy.week,
+            );
+        }
+        fifty_hours.evaluate_weekly_rules(std::slice::from_ref(&forty_five_hours), &policy.week);
+
+        assert!(!twelve_hours.has_violation(ViolationKind::ExceededMaxHours));
+        assert!(twelve_hours.has_violation(ViolationKind::ViolatedInterJourneyRest));
+        assert!(fourteen_hours.has_violation(ViolationKind::ExceededMaxHours));
+        assert!(!forty_five_hours.has_violation(ViolationKind::ExceededAverageWeeklyHours));
+        assert!(!fifty_hours.has_violation(ViolationKind::ExceededMaxWeeklyHours));
+        assert!(!fifty_hours.has_violation(ViolationKind::ExceededAverageWeeklyHours));
+
+        let mut sixty_hours = WorkWeek::new();
+        for day in [22, 23, 24, 25, 26] {
+            sixty_hours.append_day(
+                &fixed_day(day, &[(6, 360), (13, 360)], &policy),
+                &policy.week,
+            );
+        }
+        let previous_weeks = [forty_five_hours, fifty_hours];
+        sixty_hours.evaluate_weekly_rules(&previous_weeks, &policy.week);
+        assert!(sixty_hours.has_violation(ViolationKind::ExceededAverageWeeklyHours));
+
+        let seventeen_week_period = PolicyPreset::EuWorkingTimeDirective.policy().week;
+        sixty_hours.evaluate_weekly_rules(&previous_weeks, &seventeen_week_period);
+        assert!(!sixty_hours.has_violation(ViolationKind::ExceededAverageWeeklyHours));
+    }
+
+    #[test]
+    fn us_flsa_preset_rules() {
+        let policy = PolicyPreset::UsFlsa.policy();
+
+        let long_day = fixed_day(8, &[(7, 720)], &policy);
+        let week = fixed_week(&[8, 9, 10, 11, 12], &[(8, 540)], &policy);
+        let buckets = week.premium_buckets(&PolicySchedule::new(policy.clone()));
+
+        assert!(long_day.get_violations().is_empty());
+        assert!(week.get_violations().is_empty());
+        assert_eq!(TimeDelta::hours(5).num_seconds(), buckets.overtime);
+        assert_eq!(TimeDelta::hours(40).num_seconds(), buckets.regular);
+    }
+
+    #[test]
+    fn presets_keep_six_and_seven_day_weeks() {
+        let flsa = PolicyPreset::UsFlsa.policy();
+        let six_days = fixed_week(&[8, 9, 10, 11, 12, 13], &[(8, 480)], &flsa);
+        let buckets = six_days.premium_buckets(&PolicySchedule::new(flsa.clone()));
+
+        assert_eq!(6, six_days.workdays.len());
+        assert_eq!(TimeDelta::hours(8).num_seconds(), buckets.overtime);
+        assert_eq!(TimeDelta::hours(40).num_seconds(), buckets.regular);
+
+        let eu = PolicyPreset::EuWorkingTimeDirective.policy();
+        let six_days = fixed_week(&[8, 9, 10, 11, 12, 13], &[(8, 360)], &eu);
+        let seven_days = fixed_week(&[8, 9, 10, 11, 12, 13, 14], &[(8, 360)], &eu);
+
+        assert!(!six_days.has_violation(ViolationKind::ExceededMaxConsecutiveWorkdays));
+        assert!(seven_days.has_violation(ViolationKind::ExceededMaxConsecutiveWorkdays));
+    }
+}
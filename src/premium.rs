@@ -0,0 +1,225 @@
+use std::{fmt::Display, ops::AddAssign};
+
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+
+use crate::{delta_hours::format_seconds, policy::PremiumPolicy, work_journey::WorkJourney};
+
+/// Worked seconds split into payroll buckets. `regular`, `overtime` and
+/// `sunday_holiday` partition the worked time, while `night` overlaps them and
+/// only drives the night surcharge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PremiumBuckets {
+    pub regular: i64,
+    pub overtime: i64,
+    pub sunday_holiday: i64,
+    pub night: i64,
+}
+
+impl PremiumBuckets {
+    pub fn from_journeys(
+        journeys: &[WorkJourney],
+        expected_hours: TimeDelta,
+        policy: &PremiumPolicy,
+    ) -> PremiumBuckets {
+        let mut regular = TimeDelta::zero();
+        let mut overtime = TimeDelta::zero();
+        let mut sunday_holiday = TimeDelta::zero();
+        let mut night = TimeDelta::zero();
+        let mut worked = TimeDelta::zero();
+
+        for journey in journeys {
+            let mut cursor = journey.get_starting_time();
+            let ending_time = journey.get_ending_time();
+
+            while cursor < ending_time {
+                let mut segment_end = next_boundary(cursor, policy).min(ending_time);
+                if worked < expected_hours {
+                    segment_end = segment_end.min(cursor + (expected_hours - worked));
+                }
+                let segment = segment_end - cursor;
+
+                if policy.is_sunday_or_holiday(cursor.date_naive()) {
+                    sunday_holiday += segment;
+                } else if worked >= expected_hours {
+                    overtime += segment;
+                } else {
+                    regular += segment;
+                }
+
+                if policy.is_night(cursor.time()) {
+                    night += segment;
+                }
+
+                worked += segment;
+                cursor = segment_end;
+            }
+        }
+
+        PremiumBuckets {
+            regular: regular.num_seconds(),
+            overtime: overtime.num_seconds(),
+            sunday_holiday: sunday_holiday.num_seconds(),
+            night: night.num_seconds(),
+        }
+    }
+
+    /// Moves regular time above the weekly threshold into overtime. A zero
+    /// threshold leaves the buckets untouched.
+    pub fn apply_weekly_overtime(&mut self, weekly_threshold: TimeDelta) {
+        if weekly_threshold <= TimeDelta::zero() {
+            return;
+        }
+        let excess = (self.worked_seconds() - weekly_threshold.num_seconds()).max(0);
+        let moved = excess.min(self.regular);
+        self.regular -= moved;
+        self.overtime += moved;
+    }
+
+    pub fn worked_seconds(&self) -> i64 {
+        self.regular + self.overtime + self.sunday_holiday
+    }
+
+    /// Night seconds converted to reduced night hours, so 52m30s of real time
+    /// counts as a full hour with the default policy.
+    pub fn night_equivalent_seconds(&self, policy: &PremiumPolicy) -> i64 {
+        let night_hour = policy.night_hour.num_seconds();
+        if night_hour <= 0 {
+            return self.night;
+        }
+        self.night * TimeDelta::hours(1).num_seconds() / night_hour
+    }
+
+    pub fn weighted_hours(&self, policy: &PremiumPolicy) -> f64 {
+        let base = self.regular as f64 * policy.regular_multiplier
+            + self.overtime as f64 * policy.overtime_multiplier
+            + self.sunday_holiday as f64 * policy.sunday_holiday_multiplier;
+        let night_surcharge = self.night_equivalent_seconds(policy) as f64
+            * policy.night_multiplier
+            - self.night as f64;
+
+        (base + night_surcharge) / TimeDelta::hours(1).num_seconds() as f64
+    }
+}
+
+impl AddAssign for PremiumBuckets {
+    fn add_assign(&mut self, rhs: Self) {
+        self.regular += rhs.regular;
+        self.overtime += rhs.overtime;
+        self.sunday_holiday += rhs.sunday_holiday;
+        self.night += rhs.night;
+    }
+}
+
+impl Display for PremiumBuckets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "regular {}, overtime {}, sunday/holiday {}, night {}",
+            format_seconds(self.regular),
+            format_seconds(self.overtime),
+            format_seconds(self.sunday_holiday),
+            format_seconds(self.night)
+        )
+    }
+}
+
+fn next_boundary(current_time: DateTime<Utc>, policy: &PremiumPolicy) -> DateTime<Utc> {
+    [NaiveTime::MIN, policy.night_start, policy.night_end]
+        .into_iter()
+        .map(|boundary| next_occurrence(current_time, boundary))
+        .min()
+        .expect("Boundary list is never empty.")
+}
+
+fn next_occurrence(current_time: DateTime<Utc>, time: NaiveTime) -> DateTime<Utc> {
+    let candidate = current_time.date_naive().and_time(time).and_utc();
+    if candidate > current_time {
+        candidate
+    } else {
+        candidate + TimeDelta::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    use crate::{policy::PremiumPolicy, work_journey::WorkJourney};
+
+    use super::PremiumBuckets;
+
+    #[test]
+    fn regular_and_overtime_split() {
+        let start = Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap();
+        let journey = WorkJourney::new(start, start + TimeDelta::hours(10)).unwrap();
+
+        let buckets = PremiumBuckets::from_journeys(
+            &[journey],
+            TimeDelta::hours(8),
+            &PremiumPolicy::default(),
+        );
+
+        assert_eq!(TimeDelta::hours(8).num_seconds(), buckets.regular);
+        assert_eq!(TimeDelta::hours(2).num_seconds(), buckets.overtime);
+        assert_eq!(0, buckets.sunday_holiday);
+        assert_eq!(0, buckets.night);
+    }
+
+    #[test]
+    fn sunday_and_holiday_bucket() {
+        let sunday = Utc.with_ymd_and_hms(2024, 7, 14, 8, 0, 0).unwrap();
+        let holiday = Utc.with_ymd_and_hms(2024, 7, 15, 8, 0, 0).unwrap();
+        let policy = PremiumPolicy {
+            holidays: vec![holiday.date_naive()],
+            ..Default::default()
+        };
+
+        let sunday_buckets = PremiumBuckets::from_journeys(
+            &[WorkJourney::new(sunday, sunday + TimeDelta::hours(4)).unwrap()],
+            TimeDelta::hours(8),
+            &policy,
+        );
+        let holiday_buckets = PremiumBuckets::from_journeys(
+            &[WorkJourney::new(holiday, holiday + TimeDelta::hours(4)).unwrap()],
+            TimeDelta::hours(8),
+            &policy,
+        );
+
+        assert_eq!(
+            TimeDelta::hours(4).num_seconds(),
+            sunday_buckets.sunday_holiday
+        );
+        assert_eq!(
+            TimeDelta::hours(4).num_seconds(),
+            holiday_buckets.sunday_holiday
+        );
+        assert_eq!(0, holiday_buckets.regular);
+    }
+
+    #[test]
+    fn night_hours_with_reduced_night_hour() {
+        let start = Utc.with_ymd_and_hms(2024, 7, 10, 20, 0, 0).unwrap();
+        let journey = WorkJourney::new(start, start + TimeDelta::hours(4)).unwrap();
+        let policy = PremiumPolicy::default();
+
+        let buckets = PremiumBuckets::from_journeys(&[journey], TimeDelta::hours(8), &policy);
+
+        assert_eq!(TimeDelta::hours(2).num_seconds(), buckets.night);
+        assert_eq!(TimeDelta::hours(4).num_seconds(), buckets.regular);
+        assert_eq!(
+            TimeDelta::hours(2).num_seconds() * 3600 / 3150,
+            buckets.night_equivalent_seconds(&policy)
+        );
+    }
+
+    #[test]
+    fn weighted_hours_math() {
+        let start = Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap();
+        let journey = WorkJourney::new(start, start + TimeDelta::hours(10)).unwrap();
+        let policy = PremiumPolicy::default();
+
+        let buckets = PremiumBuckets::from_journeys(&[journey], TimeDelta::hours(8), &policy);
+
+        assert!((buckets.weighted_hours(&policy) - 11.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,462 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
+use thiserror::Error;
+
+use crate::{
+    long_term_registry::LongTermRegistry,
+    policy::PolicySchedule,
+    violations::Violation,
+    work_days::{WorkDay, WorkDayError},
+    work_journey::WorkJourney,
+    work_week::{WorkWeek, MAX_DAYS_PER_WEEK},
+};
+
+pub const DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA: TimeDelta = TimeDelta::minutes(2);
+
+#[derive(Error, Debug)]
+pub enum PunchPairingError {
+    #[error("Proposed day {0} is not after the last day in the registry.")]
+    OverlapsHistory(NaiveDate),
+    #[error("Proposed day {0} is in a period closed until {1}.")]
+    InClosedPeriod(NaiveDate, NaiveDate),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PunchAnomaly {
+    DuplicatePunch {
+        punch: DateTime<Utc>,
+        kept: DateTime<Utc>,
+    },
+    MissingPunch {
+        unpaired: DateTime<Utc>,
+    },
+    InvalidDay(WorkDayError),
+    TooManyDaysInWeek {
+        extra_day: NaiveDate,
+    },
+    RuleViolation(Violation),
+}
+
+impl PunchAnomaly {
+    /// Blocking anomalies keep the day out of the registry until the punches
+    /// are fixed; the others are only reported.
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            PunchAnomaly::MissingPunch { .. }
+                | PunchAnomaly::InvalidDay(_)
+                | PunchAnomaly::TooManyDaysInWeek { .. }
+        )
+    }
+}
+
+impl Display for PunchAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PunchAnomaly::DuplicatePunch { punch, kept } => {
+                write!(f, "Duplicated punch at {} (kept {})", punch, kept)
+            }
+            PunchAnomaly::MissingPunch { unpaired } => {
+                write!(f, "Missing clock-in or clock-out around {}", unpaired)
+            }
+            PunchAnomaly::InvalidDay(err) => write!(f, "Invalid day: {}", err),
+            PunchAnomaly::TooManyDaysInWeek { extra_day } => write!(
+                f,
+                "{} exceeds the maximum of {} days in a week",
+                extra_day, MAX_DAYS_PER_WEEK
+            ),
+            PunchAnomaly::RuleViolation(violation) => write!(f, "{}", violation),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ProposedDay {
+    pub date: NaiveDate,
+    pub journeys: Vec<WorkJourney>,
+    pub anomalies: Vec<PunchAnomaly>,
+    work_day: Option<WorkDay>,
+}
+
+impl ProposedDay {
+    pub fn is_committable(&self) -> bool {
+        self.work_day.is_some() && !self.anomalies.iter().any(PunchAnomaly::is_blocking)
+    }
+}
+
+/// Work days proposed from a flat list of badge punches, kept for review
+/// before being committed into the registry.
+#[derive(Clone, Debug)]
+pub struct PairingProposal {
+    pub days: Vec<ProposedDay>,
+}
+
+impl PairingProposal {
+    pub fn from_punches(
+        punches: &[DateTime<Utc>],
+        duplicate_tolerance: TimeDelta,
+        policy: &PolicySchedule,
+    ) -> PairingProposal {
+        let mut sorted_punches = punches.to_vec();
+        sorted_punches.sort();
+
+        let mut days: Vec<ProposedDay> = Vec::new();
+        let mut day_punches: Vec<DateTime<Utc>> = Vec::new();
+        let mut anomalies = Vec::new();
+
+        for punch in sorted_punches {
+            if let Some(last_punch) = day_punches.last() {
+                if last_punch.date_naive() != punch.date_naive() {
+                    days.push(propose_day(&day_punches, anomalies, policy));
+                    day_punches = Vec::new();
+                    anomalies = Vec::new();
+                } else if punch - *last_punch <= duplicate_tolerance {
+                    anomalies.push(PunchAnomaly::DuplicatePunch {
+                        punch,
+                        kept: *last_punch,
+                    });
+                    continue;
+                }
+            }
+            day_punches.push(punch);
+        }
+        if !day_punches.is_empty() {
+            days.push(propose_day(&day_punches, anomalies, policy));
+        }
+
+        flag_overfull_weeks(&mut days);
+
+        PairingProposal { days }
+    }
+
+    pub fn anomalies(&self) -> Vec<(NaiveDate, &PunchAnomaly)> {
+        self.days
+            .iter()
+            .flat_map(|day| day.anomalies.iter().map(move |anomaly| (day.date, anomaly)))
+            .collect()
+    }
+
+    /// Proposed weeks, grouped by ISO week, with the committable days only.
+    /// When the first day shares the ISO week of the last previous week, the
+    /// first proposed week continues it and replaces it.
+    pub fn weeks(&self, previous_weeks: &[WorkWeek], policy: &PolicySchedule) -> Vec<WorkWeek> {
+        let continued_week = self.continued_week(previous_weeks);
+        let mut context = previous_weeks.to_vec();
+        let mut weeks: Vec<WorkWeek> = Vec::new();
+        if continued_week.is_some() {
+            weeks.extend(context.pop());
+        }
+        let mut last_date = continued_week
+            .and_then(WorkWeek::last_clock_out_last_day_in_week)
+            .map(|last_clock_out| last_clock_out.date_naive());
+
+        for day in self.committable_days() {
+            let Some(work_day) = &day.work_day else {
+                continue;
+            };
+            if last_date.is_none_or(|date| date.iso_week() != day.date.iso_week()) {
+                weeks.push(WorkWeek::new());
+            }
+            if let Some(week) = weeks.last_mut() {
+                week.append_day(work_day, &policy.policy_at(day.date).week);
+            }
+            last_date = Some(day.date);
+        }
+
+        for week in weeks.iter_mut() {
+            let week_policy = &policy.policy_for_week(week).week;
+            week.evaluate_weekly_rules(&context, week_policy);
+            context.push(week.clone());
+        }
+
+        weeks
+    }
+
+    /// Appends the committable days to the registry and returns the dates that
+    /// were left out because of blocking anomalies. Every committed day must
+    /// come after the registry and after the closed periods.
+    pub fn commit_into(
+        &self,
+        long_term_registry: &mut LongTermRegistry,
+        policy: &PolicySchedule,
+    ) -> Result<Vec<NaiveDate>, PunchPairingError> {
+        let mut previous_date = long_term_registry
+            .last_clock_out_last_week()
+            .map(|last_clock_out| last_clock_out.date_naive());
+        let closed_until = long_term_registry.closed_until();
+        for day in self.committable_days() {
+            if let Some(closed_on) = closed_until.filter(|closed_on| day.date <= *closed_on) {
+                return Err(PunchPairingError::InClosedPeriod(day.date, closed_on));
+            }
+            if previous_date.is_some_and(|date| day.date <= date) {
+                return Err(PunchPairingError::OverlapsHistory(day.date));
+            }
+            previous_date = Some(day.date);
+        }
+
+        let weeks = self.weeks(&long_term_registry.history, policy);
+        if self.continued_week(&long_term_registry.history).is_some() {
+            long_term_registry.history.pop();
+        }
+        long_term_registry.history.extend(weeks);
+
+        Ok(self
+            .days
+            .iter()
+            .filter(|day| !day.is_committable())
+            .map(|day| day.date)
+            .collect())
+    }
+
+    fn committable_days(&self) -> impl Iterator<Item = &ProposedDay> {
+        self.days.iter().filter(|day| day.is_committable())
+    }
+
+    /// Last previous week, when the first committable day falls in its ISO
+    /// week.
+    fn continued_week<'a>(&self, previous_weeks: &'a [WorkWeek]) -> Option<&'a WorkWeek> {
+        let first_date = self.committable_days().next()?.date;
+        previous_weeks.last().filter(|week| {
+            week.last_clock_out_last_day_in_week()
+                .is_some_and(|last_clock_out| {
+                    last_clock_out.date_naive().iso_week() == first_date.iso_week()
+                })
+        })
+    }
+}
+
+impl Display for PairingProposal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for day in &self.days {
+            let journeys: Vec<String> = day
+                .journeys
+                .iter()
+                .map(|journey| {
+                    format!(
+                        "{}-{}",
+                        journey.get_starting_time().format("%H:%M"),
+                        journey.get_ending_time().format("%H:%M")
+                    )
+                })
+                .collect();
+            writeln!(
+                f,
+                "{} ({}) -> {}{}",
+                day.date,
+                day.date.format("%a"),
+                journeys.join(", "),
+                if day.is_committable() {
+                    ""
+                } else {
+                    " [needs review]"
+                }
+            )?;
+            for anomaly in &day.anomalies {
+                writeln!(f, "    ! {}", anomaly)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn propose_day(
+    punches: &[DateTime<Utc>],
+    mut anomalies: Vec<PunchAnomaly>,
+    policy: &PolicySchedule,
+) -> ProposedDay {
+    let date = punches[0].date_naive();
+    let mut journeys = Vec::new();
+
+    for pair in punches.chunks(2) {
+        match pair {
+            [starting_time, ending_time] => {
+                if let Ok(journey) = WorkJourney::new(*starting_time, *ending_time) {
+                    journeys.push(journey);
+                }
+            }
+            [unpaired] => anomalies.push(PunchAnomaly::MissingPunch {
+                unpaired: *unpaired,
+            }),
+            _ => (),
+        }
+    }
+
+    let work_day = match WorkDay::new(&journeys, &policy.policy_at(date).day) {
+        Ok(work_day) => {
+            anomalies.extend(
+                work_day
+                    .get_violations()
+                    .into_iter()
+                    .map(PunchAnomaly::RuleViolation),
+            );
+            Some(work_day)
+        }
+        Err(err) => {
+            anomalies.push(PunchAnomaly::InvalidDay(err));
+            None
+        }
+    };
+
+    ProposedDay {
+        date,
+        journeys,
+        anomalies,
+        work_day,
+    }
+}
+
+fn flag_overfull_weeks(days: &mut [ProposedDay]) {
+    let mut days_in_week = 0;
+    let mut last_date: Option<NaiveDate> = None;
+
+    for day in days.iter_mut() {
+        if last_date.is_some_and(|date| date.iso_week() == day.date.iso_week()) {
+            days_in_week += 1;
+        } else {
+            days_in_week = 1;
+        }
+        if days_in_week > MAX_DAYS_PER_WEEK {
+            day.anomalies.push(PunchAnomaly::TooManyDaysInWeek {
+                extra_day: day.date,
+            });
+        }
+        last_date = Some(day.date);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone, Utc};
+
+    use crate::{long_term_registry::LongTermRegistry, policy::PolicySchedule};
+
+    use super::{
+        PairingProposal, PunchAnomaly, PunchPairingError, DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA,
+    };
+
+    fn punch(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn punches_are_paired_per_day() {
+        let punches = [
+            punch(9, 13, 0),
+            punch(8, 9, 0),
+            punch(8, 12, 0),
+            punch(8, 13, 0),
+            punch(8, 18, 0),
+            punch(9, 9, 0),
+            punch(9, 12, 0),
+            punch(9, 18, 0),
+        ];
+
+        let proposal = PairingProposal::from_punches(
+            &punches,
+            DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA,
+            &PolicySchedule::default(),
+        );
+
+        assert_eq!(2, proposal.days.len());
+        assert_eq!(2, proposal.days[0].journeys.len());
+        assert_eq!(
+            TimeDelta::hours(3),
+            proposal.days[0].journeys[0].worked_hours()
+        );
+        assert!(proposal.days.iter().all(|day| day.is_committable()));
+    }
+
+    #[test]
+    fn duplicated_and_missing_punches_are_reported() {
+        let punches = [
+            punch(8, 9, 0),
+            punch(8, 9, 1),
+            punch(8, 12, 0),
+            punch(8, 13, 0),
+        ];
+
+        let proposal = PairingProposal::from_punches(
+            &punches,
+            DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA,
+            &PolicySchedule::default(),
+        );
+        let anomalies = proposal.anomalies();
+
+        assert!(anomalies.iter().any(|(_, anomaly)| **anomaly
+            == PunchAnomaly::DuplicatePunch {
+                punch: punch(8, 9, 1),
+                kept: punch(8, 9, 0)
+            }));
+        assert!(anomalies.iter().any(|(_, anomaly)| **anomaly
+            == PunchAnomaly::MissingPunch {
+                unpaired: punch(8, 13, 0)
+            }));
+        assert!(!proposal.days[0].is_committable());
+    }
+
+    #[test]
+    fn proposal_is_committed_by_week() {
+        let mut punches = Vec::new();
+        for day in [11, 12, 15, 16] {
+            punches.extend([
+                punch(day, 9, 0),
+                punch(day, 12, 0),
+                punch(day, 13, 0),
+                punch(day, 18, 0),
+            ]);
+        }
+        punches.push(punch(17, 9, 0));
+        let mut long_term_registry = LongTermRegistry::new();
+
+        let proposal = PairingProposal::from_punches(
+            &punches,
+            DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA,
+            &PolicySchedule::default(),
+        );
+        let skipped = proposal
+            .commit_into(&mut long_term_registry, &PolicySchedule::default())
+            .unwrap();
+
+        assert_eq!(vec![NaiveDate::from_ymd_opt(2024, 7, 17).unwrap()], skipped);
+        assert_eq!(2, long_term_registry.history.len());
+        assert_eq!(
+            TimeDelta::hours(4 * 8).num_seconds(),
+            long_term_registry.worked_hours()
+        );
+        assert!(proposal
+            .commit_into(&mut long_term_registry, &PolicySchedule::default())
+            .is_err());
+    }
+
+    #[test]
+    fn import_continues_the_last_week_and_checks_every_day() {
+        let full_day = |day| [punch(day, 9, 0), punch(day, 17, 0)];
+        let propose = |punches: &[DateTime<Utc>]| {
+            PairingProposal::from_punches(
+                punches,
+                DUPLICATE_PUNCH_TOLERANCE_TIME_DELTA,
+                &PolicySchedule::default(),
+            )
+        };
+        let mut long_term_registry = LongTermRegistry::new();
+        propose(&[full_day(8), full_day(9)].concat())
+            .commit_into(&mut long_term_registry, &PolicySchedule::default())
+            .unwrap();
+
+        propose(&[full_day(10), full_day(15)].concat())
+            .commit_into(&mut long_term_registry, &PolicySchedule::default())
+            .unwrap();
+        assert_eq!(2, long_term_registry.history.len());
+        assert_eq!(3, long_term_registry.history[0].workdays.len());
+
+        let mut unordered = propose(&[full_day(16), full_day(18)].concat());
+        unordered.days.reverse();
+        assert!(matches!(
+            unordered.commit_into(&mut long_term_registry, &PolicySchedule::default()),
+            Err(PunchPairingError::OverlapsHistory(date))
+                if date == NaiveDate::from_ymd_opt(2024, 7, 16).unwrap()
+        ));
+        assert_eq!(1, long_term_registry.history[1].workdays.len());
+    }
+}
@@ -0,0 +1,368 @@
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    policy::DayPolicy,
+    violations::Violation,
+    work_days::{WorkDay, WorkDayError},
+    work_journey::WorkJourney,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LeaveTarget {
+    /// Worked hours today match the expected hours.
+    Today,
+    /// The delta of the current week is zero.
+    ThisWeek,
+    /// The whole hour bank is zero.
+    Balance,
+}
+
+/// Rule that stops the current journey before the target is reached.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LeaveLimit {
+    MaxJourneyHours,
+    MaxDailyHours,
+    RequiredBreak,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LeaveRecommendation {
+    pub target: LeaveTarget,
+    pub leave_at: DateTime<Utc>,
+    /// Work still missing to reach the target, negative when already exceeded.
+    pub remaining: TimeDelta,
+    pub limit: Option<LeaveLimit>,
+}
+
+impl LeaveRecommendation {
+    /// Recommends when to stop the journey started at `journey_start`, given the
+    /// finished journeys of the day and the work still `remaining` for the
+    /// target when the journey started.
+    pub fn new(
+        target: LeaveTarget,
+        journey_start: DateTime<Utc>,
+        finished_journeys: &[WorkJourney],
+        remaining: TimeDelta,
+        now: DateTime<Utc>,
+        policy: &DayPolicy,
+    ) -> LeaveRecommendation {
+        let target_end = journey_start + remaining.max(TimeDelta::zero());
+        let mut leave_at = target_end;
+        let mut limit = None;
+
+        for (limit_end, limit_kind) in journey_limits(journey_start, finished_journeys, policy) {
+            if limit_end < leave_at {
+                leave_at = limit_end;
+                limit = Some(limit_kind);
+            }
+        }
+
+        LeaveRecommendation {
+            target,
+            leave_at: leave_at.max(now),
+            remaining: remaining - (now - journey_start),
+            limit,
+        }
+    }
+
+    pub fn is_reachable(&self) -> bool {
+        self.limit.is_none()
+    }
+}
+
+/// Plan for the rest of the day: when to stop the current journey, when to
+/// come back after the break and when to clock out for good.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DayPlan {
+    pub stop_by: DateTime<Utc>,
+    pub return_at: Option<DateTime<Utc>>,
+    pub clock_out: DateTime<Utc>,
+    /// Rules the planned day would still break, as evaluated by `WorkDay::new`.
+    pub violations: Vec<Violation>,
+}
+
+impl DayPlan {
+    /// `None` when the rest of the day can't be planned before midnight.
+    pub fn new(
+        journey_start: DateTime<Utc>,
+        finished_journeys: &[WorkJourney],
+        remaining: TimeDelta,
+        policy: &DayPolicy,
+    ) -> Result<Option<DayPlan>, WorkDayError> {
+        let remaining = remaining.max(TimeDelta::zero());
+        let target_end = journey_start + remaining;
+        let stop_by = journey_limits(journey_start, finished_journeys, policy)
+            .into_iter()
+            .map(|(limit_end, _)| limit_end)
+            .fold(target_end, DateTime::min)
+            .max(journey_start);
+
+        let mut journeys = finished_journeys.to_vec();
+        journeys.push(
+            WorkJourney::new(journey_start, stop_by)
+                .expect("Planned stop is never before the journey start."),
+        );
+
+        let left_after_stop = remaining - (stop_by - journey_start);
+        let (return_at, clock_out) = if left_after_stop > TimeDelta::zero() {
+            let worked_hours = finished_journeys
+                .iter()
+                .fold(TimeDelta::zero(), |acc, journey| {
+                    acc + journey.worked_hours()
+                })
+                + remaining;
+            let required_break = if worked_hours > policy.long_day_threshold {
+                policy.long_day_break
+            } else if worked_hours > policy.short_day_threshold {
+                policy.short_day_break
+            } else {
+                TimeDelta::zero()
+            };
+            let rest_taken = counted_rest(finished_journeys, journey_start, policy);
+            let return_at = stop_by + (required_break - rest_taken).max(policy.min_counted_break);
+            let clock_out = return_at + left_after_stop;
+
+            journeys.push(
+                WorkJourney::new(return_at, clock_out)
+                    .expect("Planned clock-out is after the return."),
+            );
+            (Some(return_at), clock_out)
+        } else {
+            (None, stop_by)
+        };
+
+        let day_date = finished_journeys
+            .first()
+            .map_or(journey_start, WorkJourney::get_starting_time)
+            .date_naive();
+        if clock_out.date_naive() != day_date {
+            return Ok(None);
+        }
+        let planned_day = WorkDay::new(&journeys, policy)?;
+
+        Ok(Some(DayPlan {
+            stop_by,
+            return_at,
+            clock_out,
+            violations: planned_day.get_violations(),
+        }))
+    }
+
+    pub fn needs_another_journey(&self) -> bool {
+        self.return_at.is_some()
+    }
+}
+
+/// Latest ends for a journey started at `journey_start` allowed by each rule.
+pub fn journey_limits(
+    journey_start: DateTime<Utc>,
+    finished_journeys: &[WorkJourney],
+    policy: &DayPolicy,
+) -> Vec<(DateTime<Utc>, LeaveLimit)> {
+    let worked_hours = finished_journeys
+        .iter()
+        .fold(TimeDelta::zero(), |acc, journey| {
+            acc + journey.worked_hours()
+        });
+    let rest_taken = counted_rest(finished_journeys, journey_start, policy);
+
+    let mut limits = vec![
+        (
+            journey_start + policy.max_journey_hours,
+            LeaveLimit::MaxJourneyHours,
+        ),
+        (
+            journey_start + (policy.max_hours - worked_hours),
+            LeaveLimit::MaxDailyHours,
+        ),
+    ];
+
+    let break_thresholds = [
+        (policy.short_day_threshold, policy.short_day_break),
+        (policy.long_day_threshold, policy.long_day_break),
+    ];
+    if let Some((threshold, _)) = break_thresholds
+        .into_iter()
+        .find(|(_, required_break)| rest_taken < *required_break)
+    {
+        limits.push((
+            journey_start + (threshold - worked_hours).max(TimeDelta::zero()),
+            LeaveLimit::RequiredBreak,
+        ));
+    }
+
+    limits
+}
+
+/// Rest counted towards the break rules, up to the start of the current
+/// journey.
+pub fn counted_rest(
+    finished_journeys: &[WorkJourney],
+    journey_start: DateTime<Utc>,
+    policy: &DayPolicy,
+) -> TimeDelta {
+    let ends = finished_journeys.iter().map(WorkJourney::get_ending_time);
+    let starts = finished_journeys
+        .iter()
+        .skip(1)
+        .map(WorkJourney::get_starting_time)
+        .chain(std::iter::once(journey_start));
+
+    ends.zip(starts)
+        .map(|(end, start)| start - end)
+        .filter(|rest| *rest >= policy.min_counted_break)
+        .fold(TimeDelta::zero(), |acc, rest| acc + rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+
+    use crate::{policy::DayPolicy, violations::ViolationKind, work_journey::WorkJourney};
+
+    use super::{DayPlan, LeaveLimit, LeaveRecommendation, LeaveTarget};
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 10, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn first_journey_is_stopped_by_the_required_break() {
+        let recommendation = LeaveRecommendation::new(
+            LeaveTarget::Today,
+            at(8, 0),
+            &[],
+            TimeDelta::hours(8),
+            at(9, 0),
+            &DayPolicy::default(),
+        );
+
+        assert_eq!(at(12, 0), recommendation.leave_at);
+        assert_eq!(Some(LeaveLimit::RequiredBreak), recommendation.limit);
+        assert_eq!(TimeDelta::hours(7), recommendation.remaining);
+    }
+
+    #[test]
+    fn second_journey_reaches_the_target() {
+        let morning = WorkJourney::new(at(8, 0), at(12, 0)).unwrap();
+
+        let recommendation = LeaveRecommendation::new(
+            LeaveTarget::ThisWeek,
+            at(13, 0),
+            &[morning],
+            TimeDelta::hours(5),
+            at(13, 30),
+            &DayPolicy::default(),
+        );
+
+        assert_eq!(at(18, 0), recommendation.leave_at);
+        assert!(recommendation.is_reachable());
+    }
+
+    #[test]
+    fn balance_is_capped_by_the_daily_maximum() {
+        let morning = WorkJourney::new(at(8, 0), at(13, 0)).unwrap();
+
+        let recommendation = LeaveRecommendation::new(
+            LeaveTarget::Balance,
+            at(14, 0),
+            &[morning],
+            TimeDelta::hours(7),
+            at(14, 30),
+            &DayPolicy::default(),
+        );
+
+        assert_eq!(at(19, 0), recommendation.leave_at);
+        assert_eq!(Some(LeaveLimit::MaxDailyHours), recommendation.limit);
+    }
+
+    #[test]
+    fn exceeded_target_means_leaving_now() {
+        let recommendation = LeaveRecommendation::new(
+            LeaveTarget::Today,
+            at(13, 0),
+            &[WorkJourney::new(at(8, 0), at(12, 0)).unwrap()],
+            TimeDelta::hours(-1),
+            at(14, 0),
+            &DayPolicy::default(),
+        );
+
+        assert_eq!(at(14, 0), recommendation.leave_at);
+        assert_eq!(TimeDelta::hours(-2), recommendation.remaining);
+    }
+
+    #[test]
+    fn day_plan_places_the_required_break() {
+        let plan = DayPlan::new(at(8, 0), &[], TimeDelta::hours(8), &DayPolicy::default())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(at(12, 0), plan.stop_by);
+        assert_eq!(Some(at(13, 0)), plan.return_at);
+        assert_eq!(at(17, 0), plan.clock_out);
+        assert!(plan.violations.is_empty());
+    }
+
+    #[test]
+    fn day_plan_counts_the_rest_already_taken() {
+        let morning = WorkJourney::new(at(8, 0), at(10, 0)).unwrap();
+
+        let plan = DayPlan::new(
+            at(10, 30),
+            &[morning],
+            TimeDelta::hours(6),
+            &DayPolicy::default(),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(at(14, 30), plan.stop_by);
+        assert_eq!(Some(at(15, 0)), plan.return_at);
+        assert_eq!(at(17, 0), plan.clock_out);
+        assert!(plan.violations.is_empty());
+    }
+
+    #[test]
+    fn day_plan_without_break_ends_in_the_current_journey() {
+        let morning = WorkJourney::new(at(8, 0), at(12, 0)).unwrap();
+
+        let plan = DayPlan::new(
+            at(13, 0),
+            &[morning],
+            TimeDelta::hours(4),
+            &DayPolicy::default(),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(!plan.needs_another_journey());
+        assert_eq!(at(17, 0), plan.clock_out);
+    }
+
+    #[test]
+    fn day_plan_reports_the_rules_it_cannot_meet() {
+        let policy = DayPolicy {
+            max_hours: TimeDelta::hours(14),
+            ..Default::default()
+        };
+
+        let plan = DayPlan::new(at(6, 0), &[], TimeDelta::hours(13), &policy)
+            .unwrap()
+            .unwrap();
+
+        assert!(plan
+            .violations
+            .iter()
+            .any(|violation| violation.kind == ViolationKind::ExceededMaxJourneyLength));
+    }
+
+    #[test]
+    fn day_plan_is_not_made_past_midnight() {
+        let late_start = Utc.with_ymd_and_hms(2024, 7, 10, 20, 0, 0).unwrap();
+
+        assert_eq!(
+            None,
+            DayPlan::new(late_start, &[], TimeDelta::hours(8), &DayPolicy::default()).unwrap()
+        );
+    }
+}
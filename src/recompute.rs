@@ -0,0 +1,335 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+
+use crate::{
+    delta_hours::{format_seconds, DeltaHours},
+    policy::PolicySchedule,
+    violations::Violation,
+    work_days::{WorkDay, WorkDayError},
+    work_week::WorkWeek,
+};
+
+/// Weeks rebuilt from their raw journeys under the policy schedule, kept for
+/// review before they replace the recorded ones. Days in closed periods, and
+/// days whose journeys no longer build a valid day, are kept as recorded.
+#[derive(Clone)]
+pub struct Recomputation {
+    pub weeks: Vec<WorkWeek>,
+    pub changes: Vec<DayChange>,
+    /// Changes left out because their day is in a closed period.
+    pub closed_changes: Vec<DayChange>,
+    /// Days that could not be rebuilt, with the reason. Days stored without
+    /// journeys have no date.
+    pub failures: Vec<(Option<NaiveDate>, WorkDayError)>,
+    pub delta_before: DeltaHours,
+    pub delta_after: DeltaHours,
+}
+
+/// What recomputing changed on a single date.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DayChange {
+    pub date: NaiveDate,
+    pub worked_before: i64,
+    pub worked_after: i64,
+    pub expected_before: i64,
+    pub expected_after: i64,
+    pub added: Vec<Violation>,
+    pub removed: Vec<Violation>,
+}
+
+impl Recomputation {
+    pub fn new(
+        weeks: &[WorkWeek],
+        policy: &PolicySchedule,
+        closed_until: Option<NaiveDate>,
+    ) -> Recomputation {
+        let mut rebuilt_weeks: Vec<WorkWeek> = Vec::with_capacity(weeks.len());
+        let mut failures = Vec::new();
+
+        for week in weeks {
+            let mut rebuilt_week = WorkWeek::new();
+            for day in &week.workdays {
+                let Some(date) = day.date() else {
+                    failures.push((None, WorkDayError::EmptyDay));
+                    rebuilt_week.workdays.push(day.clone());
+                    continue;
+                };
+                let day_policy = policy.policy_at(date);
+                let rebuilt_day =
+                    WorkDay::new(day.get_journeys(), &day_policy.day).unwrap_or_else(|err| {
+                        failures.push((Some(date), err));
+                        day.clone()
+                    });
+                rebuilt_week.append_day(&rebuilt_day, &day_policy.week);
+            }
+            let week_policy = &policy.policy_for_week(&rebuilt_week).week;
+            rebuilt_week.evaluate_weekly_rules(&rebuilt_weeks, week_policy);
+
+            for violation in week_violations(week) {
+                if let Some(justification) = &violation.justification {
+                    rebuilt_week.justify_violation(
+                        violation.date,
+                        violation.kind,
+                        justification,
+                        violation.acknowledged,
+                    );
+                }
+            }
+            rebuilt_weeks.push(rebuilt_week);
+        }
+
+        let is_closed = |date: NaiveDate| closed_until.is_some_and(|closed_on| date <= closed_on);
+        let (closed_changes, changes): (Vec<DayChange>, Vec<DayChange>) =
+            day_changes(weeks, &rebuilt_weeks)
+                .into_iter()
+                .partition(|change| is_closed(change.date));
+        for (week, rebuilt_week) in weeks.iter().zip(rebuilt_weeks.iter_mut()) {
+            if week
+                .last_clock_out_last_day_in_week()
+                .is_some_and(|last_clock_out| is_closed(last_clock_out.date_naive()))
+            {
+                *rebuilt_week = week.clone();
+                continue;
+            }
+            for (day, rebuilt_day) in week.workdays.iter().zip(rebuilt_week.workdays.iter_mut()) {
+                if day.date().is_some_and(is_closed) {
+                    *rebuilt_day = day.clone();
+                }
+            }
+        }
+
+        Recomputation {
+            changes,
+            closed_changes,
+            failures,
+            delta_before: total_delta(weeks),
+            delta_after: total_delta(&rebuilt_weeks),
+            weeks: rebuilt_weeks,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.closed_changes.is_empty() && self.failures.is_empty()
+    }
+}
+
+impl Display for Recomputation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(
+                f,
+                "{}: worked {} -> {}, expected {} -> {}",
+                change.date,
+                format_seconds(change.worked_before),
+                format_seconds(change.worked_after),
+                format_seconds(change.expected_before),
+                format_seconds(change.expected_after)
+            )?;
+            for violation in &change.removed {
+                writeln!(f, "    - {}", violation)?;
+            }
+            for violation in &change.added {
+                writeln!(f, "    + {}", violation)?;
+            }
+        }
+        for change in &self.closed_changes {
+            writeln!(
+                f,
+                "{}: in a closed period, kept as recorded (worked {} -> {})",
+                change.date,
+                format_seconds(change.worked_before),
+                format_seconds(change.worked_after)
+            )?;
+        }
+        for (date, err) in &self.failures {
+            match date {
+                Some(date) => write!(f, "{}", date)?,
+                None => write!(f, "Day without journeys")?,
+            }
+            writeln!(f, ": could not be rebuilt, kept as recorded ({})", err)?;
+        }
+        writeln!(f, "Worked delta before: {}", self.delta_before)?;
+        writeln!(f, "Worked delta after: {}", self.delta_after)
+    }
+}
+
+fn week_violations(week: &WorkWeek) -> Vec<Violation> {
+    week.workdays
+        .iter()
+        .flat_map(WorkDay::get_violations)
+        .chain(week.get_violations())
+        .collect()
+}
+
+fn total_delta(weeks: &[WorkWeek]) -> DeltaHours {
+    DeltaHours::new(
+        weeks
+            .iter()
+            .flat_map(|week| week.workdays.iter())
+            .map(|day| day.expected_hours() - day.worked_hours())
+            .sum(),
+    )
+}
+
+/// Compares the rule, date and measures of each violation, ignoring the
+/// justifications carried over.
+fn same_violation(violation: &Violation, other: &Violation) -> bool {
+    violation.is_same_occurrence(other)
+        && violation.measured == other.measured
+        && violation.limit == other.limit
+}
+
+fn day_changes(before: &[WorkWeek], after: &[WorkWeek]) -> Vec<DayChange> {
+    let before_violations: Vec<Violation> = before.iter().flat_map(week_violations).collect();
+    let after_violations: Vec<Violation> = after.iter().flat_map(week_violations).collect();
+    let days_before = before.iter().flat_map(|week| week.workdays.iter());
+    let days_after = after.iter().flat_map(|week| week.workdays.iter());
+
+    days_before
+        .zip(days_after)
+        .filter_map(|(day_before, day_after)| {
+            let date = day_before.date()?;
+            let on_date = |violations: &[Violation]| -> Vec<Violation> {
+                violations
+                    .iter()
+                    .filter(|violation| violation.date == date)
+                    .cloned()
+                    .collect()
+            };
+            let (previous, current) = (on_date(&before_violations), on_date(&after_violations));
+            let removed: Vec<Violation> = previous
+                .iter()
+                .filter(|violation| !current.iter().any(|other| same_violation(violation, other)))
+                .cloned()
+                .collect();
+            let added: Vec<Violation> = current
+                .iter()
+                .filter(|violation| {
+                    !previous
+                        .iter()
+                        .any(|other| same_violation(violation, other))
+                })
+                .cloned()
+                .collect();
+
+            let change = DayChange {
+                date,
+                worked_before: day_before.worked_hours(),
+                worked_after: day_after.worked_hours(),
+                expected_before: day_before.expected_hours(),
+                expected_after: day_after.expected_hours(),
+                added,
+                removed,
+            };
+            let unchanged = change.worked_before == change.worked_after
+                && change.expected_before == change.expected_after
+                && change.added.is_empty()
+                && change.removed.is_empty();
+            (!unchanged).then_some(change)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeDelta};
+
+    use crate::{
+        policy::{PolicySchedule, WorkPolicy},
+        violations::ViolationKind,
+        work_days::WorkDayError,
+        work_week::tests::initialize_fixed_week,
+    };
+
+    use super::Recomputation;
+
+    #[test]
+    fn recompute_reports_changed_days_and_keeps_justifications() {
+        let mut week = initialize_fixed_week(2024, 7, &[8, 9, 10], 5);
+        week.justify_violation(
+            NaiveDate::from_ymd_opt(2024, 7, 8).unwrap(),
+            ViolationKind::MissingHours,
+            "Doctor appointment",
+            true,
+        );
+        let mut part_time = WorkPolicy::default();
+        part_time.day.expected_hours = TimeDelta::hours(5);
+        part_time.day.min_hours = TimeDelta::hours(4);
+        let mut schedule = PolicySchedule::default();
+        schedule.add_version(NaiveDate::from_ymd_opt(2024, 7, 9).unwrap(), part_time);
+
+        let unchanged = Recomputation::new(
+            std::slice::from_ref(&week),
+            &PolicySchedule::default(),
+            None,
+        );
+        let closed = Recomputation::new(
+            std::slice::from_ref(&week),
+            &schedule,
+            NaiveDate::from_ymd_opt(2024, 7, 9),
+        );
+        let recomputation = Recomputation::new(&[week], &schedule, None);
+
+        assert!(unchanged.is_empty());
+        assert_eq!(1, closed.changes.len());
+        assert_eq!(1, closed.closed_changes.len());
+        assert_eq!(
+            TimeDelta::hours(8).num_seconds(),
+            closed.weeks[0].workdays[1].expected_hours()
+        );
+        assert_eq!(2, recomputation.changes.len());
+        assert_eq!(
+            TimeDelta::hours(5).num_seconds(),
+            recomputation.changes[0].expected_after
+        );
+        assert_eq!(
+            ViolationKind::MissingHours,
+            recomputation.changes[0].removed[0].kind
+        );
+        assert_eq!(
+            TimeDelta::hours(9).num_seconds(),
+            recomputation.delta_before.num_seconds()
+        );
+        assert_eq!(
+            TimeDelta::hours(3).num_seconds(),
+            recomputation.delta_after.num_seconds()
+        );
+        assert!(recomputation.weeks[0].workdays[0]
+            .get_violations()
+            .iter()
+            .any(|violation| violation.kind == ViolationKind::MissingHours
+                && violation.justification.as_deref() == Some("Doctor appointment")));
+    }
+
+    #[test]
+    fn days_that_cannot_be_rebuilt_are_reported() {
+        let mut week = initialize_fixed_week(2024, 7, &[8, 9], 8);
+        let mut broken_day = serde_json::to_value(&week.workdays[1]).unwrap();
+        let journey = broken_day["journeys"][0].clone();
+        broken_day["journeys"].as_array_mut().unwrap().push(journey);
+        week.workdays[1] = serde_json::from_value(broken_day).unwrap();
+
+        let recomputation = Recomputation::new(
+            std::slice::from_ref(&week),
+            &PolicySchedule::default(),
+            None,
+        );
+
+        assert!(!recomputation.is_empty());
+        assert_eq!(
+            vec![(
+                NaiveDate::from_ymd_opt(2024, 7, 9),
+                WorkDayError::OverlappingJourneys(1)
+            )],
+            recomputation.failures
+        );
+        assert_eq!(2, recomputation.weeks[0].workdays[1].get_journeys().len());
+
+        week.workdays
+            .push(serde_json::from_str(r#"{"journeys": [], "worked_seconds": 0}"#).unwrap());
+        let recomputation = Recomputation::new(&[week], &PolicySchedule::default(), None);
+        assert_eq!((None, WorkDayError::EmptyDay), recomputation.failures[1]);
+        assert_eq!(3, recomputation.weeks[0].workdays.len());
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt::Display;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::delta_hours::format_seconds;
+
+/// Serialized under the names of `ViolationKind::name`, also reading the
+/// variant names stored by older registries.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    #[serde(alias = "ExceededMaxHours")]
+    ExceededMaxHours,
+    #[serde(alias = "MissingHours")]
+    MissingHours,
+    #[serde(alias = "ViolatedInterJourneyRest")]
+    ViolatedInterJourneyRest,
+    #[serde(alias = "MissingShortDayBreak")]
+    MissingShortDayBreak,
+    #[serde(alias = "ExceededMaxBreak")]
+    ExceededMaxBreak,
+    #[serde(alias = "ExceededMaxJourneys")]
+    ExceededMaxJourneys,
+    #[serde(alias = "ExceededMaxJourneyLength")]
+    ExceededMaxJourneyLength,
+    #[serde(alias = "InterDayRest")]
+    InterDayRest,
+    #[serde(alias = "ExceededMaxWeeklyHours")]
+    ExceededMaxWeeklyHours,
+    #[serde(alias = "ExceededAverageWeeklyHours")]
+    ExceededAverageWeeklyHours,
+    #[serde(alias = "MissingWeeklyRest")]
+    MissingWeeklyRest,
+    #[serde(alias = "ExceededMaxConsecutiveWorkdays")]
+    ExceededMaxConsecutiveWorkdays,
+    #[serde(alias = "ExceededSundayWorkFrequency")]
+    ExceededSundayWorkFrequency,
+}
+
+impl ViolationKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            ViolationKind::MissingHours
+            | ViolationKind::ExceededMaxBreak
+            | ViolationKind::ExceededMaxJourneys
+            | ViolationKind::ExceededSundayWorkFrequency => Severity::Minor,
+            ViolationKind::ViolatedInterJourneyRest
+            | ViolationKind::MissingShortDayBreak
+            | ViolationKind::ExceededMaxJourneyLength
+            | ViolationKind::ExceededMaxWeeklyHours
+            | ViolationKind::ExceededAverageWeeklyHours
+            | ViolationKind::ExceededMaxConsecutiveWorkdays => Severity::Major,
+            ViolationKind::ExceededMaxHours
+            | ViolationKind::InterDayRest
+            | ViolationKind::MissingWeeklyRest => Severity::Critical,
+        }
+    }
+
+    /// Whether the rule is evaluated over a whole week instead of a single day
+    /// or a pair of days.
+    pub fn is_weekly(&self) -> bool {
+        matches!(
+            self,
+            ViolationKind::ExceededMaxWeeklyHours
+                | ViolationKind::ExceededAverageWeeklyHours
+                | ViolationKind::MissingWeeklyRest
+                | ViolationKind::ExceededMaxConsecutiveWorkdays
+                | ViolationKind::ExceededSundayWorkFrequency
+        )
+    }
+
+    /// Name used in exported reports, kept stable across releases.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViolationKind::ExceededMaxHours => "exceeded_max_hours",
+            ViolationKind::MissingHours => "missing_hours",
+            ViolationKind::ViolatedInterJourneyRest => "violated_inter_journey_rest",
+            ViolationKind::MissingShortDayBreak => "missing_short_day_break",
+            ViolationKind::ExceededMaxBreak => "exceeded_max_break",
+            ViolationKind::ExceededMaxJourneys => "exceeded_max_journeys",
+            ViolationKind::ExceededMaxJourneyLength => "exceeded_max_journey_length",
+            ViolationKind::InterDayRest => "inter_day_rest",
+            ViolationKind::ExceededMaxWeeklyHours => "exceeded_max_weekly_hours",
+            ViolationKind::ExceededAverageWeeklyHours => "exceeded_average_weekly_hours",
+            ViolationKind::MissingWeeklyRest => "missing_weekly_rest",
+            ViolationKind::ExceededMaxConsecutiveWorkdays => "exceeded_max_consecutive_workdays",
+            ViolationKind::ExceededSundayWorkFrequency => "exceeded_sunday_work_frequency",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ViolationKind::ExceededMaxHours => "Worked more than the daily maximum",
+            ViolationKind::MissingHours => "Worked less than the daily minimum",
+            ViolationKind::ViolatedInterJourneyRest => "Inter-journey rest was violated",
+            ViolationKind::MissingShortDayBreak => "Short-day break was not taken",
+            ViolationKind::ExceededMaxBreak => "Took a break longer than allowed",
+            ViolationKind::ExceededMaxJourneys => "Worked more journeys than allowed",
+            ViolationKind::ExceededMaxJourneyLength => "Worked a journey longer than allowed",
+            ViolationKind::InterDayRest => "Inter-day rest was violated",
+            ViolationKind::ExceededMaxWeeklyHours => "Worked more than the weekly maximum",
+            ViolationKind::ExceededAverageWeeklyHours => {
+                "Worked more than the average weekly maximum"
+            }
+            ViolationKind::MissingWeeklyRest => "Weekly rest was violated",
+            ViolationKind::ExceededMaxConsecutiveWorkdays => {
+                "Worked more consecutive days than allowed"
+            }
+            ViolationKind::ExceededSundayWorkFrequency => {
+                "Worked more consecutive Sundays than allowed"
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Severity {
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Minor => write!(f, "minor"),
+            Severity::Major => write!(f, "major"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ViolationMeasure {
+    Seconds(i64),
+    Count(usize),
+}
+
+impl Display for ViolationMeasure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViolationMeasure::Seconds(seconds) => write!(f, "{}", format_seconds(*seconds)),
+            ViolationMeasure::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+/// A single occurrence of a broken rule, with enough context to know where it
+/// happened and by how much the limit was missed.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub date: NaiveDate,
+    pub journeys: Vec<usize>,
+    pub days: Vec<NaiveDate>,
+    pub measured: ViolationMeasure,
+    pub limit: ViolationMeasure,
+    pub severity: Severity,
+    #[serde(default)]
+    pub justification: Option<String>,
+    #[serde(default)]
+    pub acknowledged: bool,
+}
+
+impl Violation {
+    pub fn new(
+        kind: ViolationKind,
+        date: NaiveDate,
+        measured: ViolationMeasure,
+        limit: ViolationMeasure,
+    ) -> Violation {
+        Violation {
+            kind,
+            date,
+            journeys: Vec::new(),
+            days: Vec::new(),
+            measured,
+            limit,
+            severity: kind.severity(),
+            justification: None,
+            acknowledged: false,
+        }
+    }
+
+    pub fn with_journeys(mut self, journeys: Vec<usize>) -> Violation {
+        self.journeys = journeys;
+        self
+    }
+
+    pub fn with_days(mut self, days: Vec<NaiveDate>) -> Violation {
+        self.days = days;
+        self
+    }
+
+    pub fn justify(&mut self, justification: String, acknowledged: bool) {
+        self.justification = Some(justification);
+        self.acknowledged = acknowledged;
+    }
+
+    /// Whether both violations are the same occurrence of the same rule, so
+    /// justifications survive a re-evaluation. Weekly violations are matched
+    /// on their ISO week, as their date moves when days are appended.
+    pub fn is_same_occurrence(&self, other: &Violation) -> bool {
+        if self.kind != other.kind {
+            return false;
+        }
+        if self.kind.is_weekly() {
+            self.date.iso_week() == other.date.iso_week()
+        } else {
+            self.date == other.date
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}) -> {}: measured {}, limit {} [{}]",
+            self.date,
+            self.date.format("%a"),
+            self.kind.description(),
+            self.measured,
+            self.limit,
+            self.severity
+        )?;
+        if let Some(justification) = &self.justification {
+            write!(f, " - justified: {}", justification)?;
+        }
+        if self.acknowledged {
+            write!(f, " [acknowledged]")?;
+        }
+        Ok(())
+    }
+}
@@ -1,108 +1,405 @@
-use chrono::{DateTime, TimeDelta, Utc};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::work_journey::WorkJourney;
+use crate::{
+    clockedin_service::EXPECTED_WORK_JOURNEY_TIME_DELTA,
+    policy::{ClockTimePolicy, DayPolicy, WorkPolicy},
+    premium::PremiumBuckets,
+    violations::{Violation, ViolationKind, ViolationMeasure},
+    work_journey::WorkJourney,
+};
 
-const MAX_JOURNEYS_PER_DAY: usize = 5;
+pub const MAX_JOURNEYS_PER_DAY: usize = 5;
 pub const MAX_HOURS_PER_JOURNEY: TimeDelta = TimeDelta::hours(6);
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
-pub enum IntraDayViolation {
-    ExceddedMaxHours,
-    MissingHours,
-    ViolatedInterJourneyRest,
-    ExceddedMaxJourneys,
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum WorkDayError {
+    #[error("A work day needs at least one journey.")]
+    EmptyDay,
+    #[error("Journey {0} overlaps the previous journey.")]
+    OverlappingJourneys(usize),
+    #[error("Journey {0} starts before the previous journey.")]
+    UnorderedJourneys(usize),
+    #[error("Journey {0} starts on a different day than the first journey.")]
+    JourneysSpanDifferentDays(usize),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "StoredWorkDay")]
 pub struct WorkDay {
     journeys: Vec<WorkJourney>,
     worked_seconds: i64,
-    violations: Vec<IntraDayViolation>,
+    /// Expected seconds under the policy in force on the day.
+    #[serde(default = "default_expected_seconds")]
+    expected_seconds: i64,
+    /// Rounding and tolerance the worked time was counted with, unknown for
+    /// days recorded before it was kept.
+    #[serde(default)]
+    clock_time: Option<ClockTimePolicy>,
+    #[serde(rename = "violation_records", default)]
+    violations: Vec<Violation>,
 }
 
 impl WorkDay {
-    pub fn new(journeys: &[WorkJourney]) -> WorkDay {
+    pub fn new(journeys: &[WorkJourney], policy: &DayPolicy) -> Result<WorkDay, WorkDayError> {
+        WorkDay::validate(journeys)?;
+
         let mut day_violations = Vec::new();
+        let date = journeys[0].get_starting_time().date_naive();
+        let all_journeys: Vec<usize> = (0..journeys.len()).collect();
 
         let worked_hours = journeys
             .iter()
             .fold(TimeDelta::zero(), |acc, item| acc + item.worked_hours())
             .num_seconds();
 
-        if worked_hours > TimeDelta::hours(6).num_seconds() {
-            let mut has_found_a_sufficient_rest = false;
-            for (index, journey) in journeys.iter().enumerate() {
-                if let Some(next_journey) = journeys.get(index + 1) {
-                    let inter_journey_rest =
-                        next_journey.get_starting_time() - journey.get_ending_time();
-
-                    if inter_journey_rest >= TimeDelta::hours(1) {
-                        has_found_a_sufficient_rest = true;
-                    }
-                }
+        let breaks: Vec<TimeDelta> = journeys
+            .windows(2)
+            .map(|pair| pair[1].get_starting_time() - pair[0].get_ending_time())
+            .collect();
+        let counted_rest = breaks
+            .iter()
+            .filter(|rest| **rest >= policy.min_counted_break)
+            .fold(TimeDelta::zero(), |acc, rest| acc + *rest);
+
+        let required_break = if worked_hours > policy.long_day_threshold.num_seconds() {
+            Some((
+                ViolationKind::ViolatedInterJourneyRest,
+                policy.long_day_break,
+            ))
+        } else if worked_hours > policy.short_day_threshold.num_seconds() {
+            Some((ViolationKind::MissingShortDayBreak, policy.short_day_break))
+        } else {
+            None
+        };
+
+        if let Some((kind, required_rest)) = required_break {
+            if counted_rest < required_rest {
+                day_violations.push(
+                    Violation::new(
+                        kind,
+                        date,
+                        ViolationMeasure::Seconds(counted_rest.num_seconds()),
+                        ViolationMeasure::Seconds(required_rest.num_seconds()),
+                    )
+                    .with_journeys(all_journeys.clone()),
+                );
             }
-            if !has_found_a_sufficient_rest {
-                day_violations.push(IntraDayViolation::ViolatedInterJourneyRest);
+        }
+
+        for (index, rest) in breaks.iter().enumerate() {
+            if *rest > policy.max_break {
+                day_violations.push(
+                    Violation::new(
+                        ViolationKind::ExceededMaxBreak,
+                        date,
+                        ViolationMeasure::Seconds(rest.num_seconds()),
+                        ViolationMeasure::Seconds(policy.max_break.num_seconds()),
+                    )
+                    .with_journeys(vec![index, index + 1]),
+                );
             }
         }
 
-        if worked_hours < TimeDelta::hours(6).num_seconds() {
-            day_violations.push(IntraDayViolation::MissingHours);
-        } else if worked_hours > TimeDelta::hours(10).num_seconds() {
-            day_violations.push(IntraDayViolation::ExceddedMaxHours);
+        if worked_hours < policy.min_hours.num_seconds() {
+            day_violations.push(
+                Violation::new(
+                    ViolationKind::MissingHours,
+                    date,
+                    ViolationMeasure::Seconds(worked_hours),
+                    ViolationMeasure::Seconds(policy.min_hours.num_seconds()),
+                )
+                .with_journeys(all_journeys.clone()),
+            );
+        } else if worked_hours > policy.max_hours.num_seconds() {
+            day_violations.push(
+                Violation::new(
+                    ViolationKind::ExceededMaxHours,
+                    date,
+                    ViolationMeasure::Seconds(worked_hours),
+                    ViolationMeasure::Seconds(policy.max_hours.num_seconds()),
+                )
+                .with_journeys(all_journeys.clone()),
+            );
         }
 
-        if journeys.len() > MAX_JOURNEYS_PER_DAY {
-            day_violations.push(IntraDayViolation::ExceddedMaxJourneys);
+        for (index, journey) in journeys.iter().enumerate() {
+            if let Some(violation) = journey_length_violation(
+                journey.get_starting_time(),
+                journey.worked_hours(),
+                policy,
+            ) {
+                day_violations.push(violation.with_journeys(vec![index]));
+            }
         }
 
-        WorkDay {
+        if journeys.len() > policy.max_journeys {
+            day_violations.push(
+                Violation::new(
+                    ViolationKind::ExceededMaxJourneys,
+                    date,
+                    ViolationMeasure::Count(journeys.len()),
+                    ViolationMeasure::Count(policy.max_journeys),
+                )
+                .with_journeys((policy.max_journeys..journeys.len()).collect()),
+            );
+        }
+
+        Ok(WorkDay {
             journeys: journeys.to_vec(),
-            worked_seconds: worked_hours,
+            worked_seconds: policy.clock_time.counted_hours(journeys).num_seconds(),
+            expected_seconds: policy.expected_hours.num_seconds(),
+            clock_time: Some(policy.clock_time.clone()),
             violations: day_violations,
-        }
+        })
     }
 
+    /// Counted seconds, after rounding and tolerance.
     pub fn worked_hours(&self) -> i64 {
         self.worked_seconds
     }
 
-    pub fn first_clock_in(&self) -> DateTime<Utc> {
-        self.journeys
-            .first()
-            .expect(
-                "Day generated without journey. There must be at least one journey in each day!",
-            )
-            .get_starting_time()
+    pub fn expected_hours(&self) -> i64 {
+        self.expected_seconds
     }
 
-    pub fn last_clock_out(&self) -> DateTime<Utc> {
+    /// Counted seconds of the journeys under the clock policy recorded with
+    /// the day, ignoring the cached value. Days recorded before clock policies
+    /// counted the raw punches.
+    pub fn counted_hours(&self) -> i64 {
+        match &self.clock_time {
+            Some(clock_time) => clock_time.counted_hours(&self.journeys).num_seconds(),
+            None => self.raw_worked_hours(),
+        }
+    }
+
+    /// Refreshes the cached worked time from the journeys.
+    pub fn recount(&mut self) {
+        self.worked_seconds = self.counted_hours();
+    }
+
+    /// Seconds between the raw punches, as recorded.
+    pub fn raw_worked_hours(&self) -> i64 {
         self.journeys
-            .last()
-            .expect(
-                "Day generated without journey. There must be at least one journey in each day!",
-            )
-            .get_ending_time()
+            .iter()
+            .fold(TimeDelta::zero(), |acc, item| acc + item.worked_hours())
+            .num_seconds()
     }
 
-    pub fn get_violations(&self) -> Vec<IntraDayViolation> {
+    pub fn get_journeys(&self) -> &[WorkJourney] {
+        &self.journeys
+    }
+
+    /// `None` for a day stored without journeys, which integrity checks report.
+    pub fn first_clock_in(&self) -> Option<DateTime<Utc>> {
+        self.journeys.first().map(WorkJourney::get_starting_time)
+    }
+
+    pub fn last_clock_out(&self) -> Option<DateTime<Utc>> {
+        self.journeys.last().map(WorkJourney::get_ending_time)
+    }
+
+    /// Date of the first clock-in.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.first_clock_in()
+            .map(|first_clock_in| first_clock_in.date_naive())
+    }
+
+    pub fn get_violations(&self) -> Vec<Violation> {
         self.violations.clone()
     }
+
+    /// Attaches the justification to the violations of `kind`, returning how
+    /// many were justified.
+    pub fn justify_violation(
+        &mut self,
+        kind: ViolationKind,
+        justification: &str,
+        acknowledged: bool,
+    ) -> usize {
+        let mut justified = 0;
+        for violation in self.violations.iter_mut().filter(|item| item.kind == kind) {
+            violation.justify(justification.to_string(), acknowledged);
+            justified += 1;
+        }
+        justified
+    }
+
+    pub fn has_violation(&self, kind: ViolationKind) -> bool {
+        self.violations
+            .iter()
+            .any(|violation| violation.kind == kind)
+    }
+
+    pub fn validate(journeys: &[WorkJourney]) -> Result<(), WorkDayError> {
+        let first_journey = journeys.first().ok_or(WorkDayError::EmptyDay)?;
+        let date = first_journey.get_starting_time().date_naive();
+
+        for (index, pair) in journeys.windows(2).enumerate() {
+            let (journey, next_journey) = (&pair[0], &pair[1]);
+
+            if next_journey.get_starting_time() < journey.get_starting_time() {
+                return Err(WorkDayError::UnorderedJourneys(index + 1));
+            }
+            if next_journey.get_starting_time() < journey.get_ending_time() {
+                return Err(WorkDayError::OverlappingJourneys(index + 1));
+            }
+            if next_journey.get_starting_time().date_naive() != date {
+                return Err(WorkDayError::JourneysSpanDifferentDays(index + 1));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn premium_buckets(&self, policy: &WorkPolicy) -> PremiumBuckets {
+        PremiumBuckets::from_journeys(
+            &self.journeys,
+            policy.daily_overtime_threshold(),
+            &policy.premium,
+        )
+    }
+}
+
+fn default_expected_seconds() -> i64 {
+    EXPECTED_WORK_JOURNEY_TIME_DELTA.num_seconds()
+}
+
+/// Stored form of a `WorkDay`, also reading the violation list kept by older
+/// registries under `violations`.
+#[derive(Deserialize)]
+struct StoredWorkDay {
+    journeys: Vec<WorkJourney>,
+    worked_seconds: i64,
+    #[serde(default = "default_expected_seconds")]
+    expected_seconds: i64,
+    #[serde(default)]
+    clock_time: Option<ClockTimePolicy>,
+    #[serde(rename = "violation_records", default)]
+    violations: Vec<Violation>,
+    #[serde(rename = "violations", default)]
+    legacy_violations: Vec<LegacyDayViolation>,
+}
+
+#[derive(Deserialize)]
+enum LegacyDayViolation {
+    ExceddedMaxHours,
+    MissingHours,
+    ViolatedInterJourneyRest,
+    ExceddedMaxJourneys,
+}
+
+impl From<StoredWorkDay> for WorkDay {
+    fn from(stored: StoredWorkDay) -> Self {
+        let legacy_violations: Vec<Violation> = match stored.journeys.first() {
+            Some(first_journey) if stored.violations.is_empty() => {
+                let date = first_journey.get_starting_time().date_naive();
+                stored
+                    .legacy_violations
+                    .iter()
+                    .map(|legacy| legacy_violation(legacy, date, &stored))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        WorkDay {
+            journeys: stored.journeys,
+            worked_seconds: stored.worked_seconds,
+            expected_seconds: stored.expected_seconds,
+            clock_time: stored.clock_time,
+            violations: if legacy_violations.is_empty() {
+                stored.violations
+            } else {
+                legacy_violations
+            },
+        }
+    }
+}
+
+/// Record of a violation stored by older registries, measured against the
+/// default limits they were checked with.
+fn legacy_violation(
+    legacy: &LegacyDayViolation,
+    date: NaiveDate,
+    stored: &StoredWorkDay,
+) -> Violation {
+    let policy = DayPolicy::default();
+    let worked = ViolationMeasure::Seconds(stored.worked_seconds);
+
+    match legacy {
+        LegacyDayViolation::ExceddedMaxHours => Violation::new(
+            ViolationKind::ExceededMaxHours,
+            date,
+            worked,
+            ViolationMeasure::Seconds(policy.max_hours.num_seconds()),
+        ),
+        LegacyDayViolation::MissingHours => Violation::new(
+            ViolationKind::MissingHours,
+            date,
+            worked,
+            ViolationMeasure::Seconds(policy.min_hours.num_seconds()),
+        ),
+        LegacyDayViolation::ViolatedInterJourneyRest => {
+            let longest_rest = stored
+                .journeys
+                .windows(2)
+                .map(|pair| pair[1].get_starting_time() - pair[0].get_ending_time())
+                .max()
+                .unwrap_or_default();
+            Violation::new(
+                ViolationKind::ViolatedInterJourneyRest,
+                date,
+                ViolationMeasure::Seconds(longest_rest.num_seconds()),
+                ViolationMeasure::Seconds(policy.long_day_break.num_seconds()),
+            )
+        }
+        LegacyDayViolation::ExceddedMaxJourneys => Violation::new(
+            ViolationKind::ExceededMaxJourneys,
+            date,
+            ViolationMeasure::Count(stored.journeys.len()),
+            ViolationMeasure::Count(policy.max_journeys),
+        ),
+    }
+}
+
+/// Flags a continuous block of work longer than the configured maximum. Also
+/// used for the journey still in progress.
+pub fn journey_length_violation(
+    starting_time: DateTime<Utc>,
+    length: TimeDelta,
+    policy: &DayPolicy,
+) -> Option<Violation> {
+    if length > policy.max_journey_hours {
+        Some(Violation::new(
+            ViolationKind::ExceededMaxJourneyLength,
+            starting_time.date_naive(),
+            ViolationMeasure::Seconds(length.num_seconds()),
+            ViolationMeasure::Seconds(policy.max_journey_hours.num_seconds()),
+        ))
+    } else {
+        None
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use chrono::{TimeDelta, Utc};
+    use chrono::{NaiveTime, TimeDelta, TimeZone, Utc};
 
-    use crate::{work_days::WorkDay, work_journey::IncompleteWorkJourney};
+    use crate::{
+        policy::{ClockTimePolicy, DayPolicy},
+        violations::{ViolationKind, ViolationMeasure},
+        work_days::{journey_length_violation, WorkDay, WorkDayError},
+        work_journey::{IncompleteWorkJourney, WorkJourney},
+    };
 
     #[test]
     fn basic_work_day_initialization() {
-        let now = Utc::now();
+        let now = mock_day_start();
         let now_plus_six = now + TimeDelta::hours(6);
-        let now_plus_seven = now + TimeDelta::hours(1);
-        let now_plus_eight = now + TimeDelta::hours(1);
+        let now_plus_seven = now_plus_six + TimeDelta::hours(1);
+        let now_plus_eight = now_plus_seven + TimeDelta::hours(1);
 
         let mut new_journey = IncompleteWorkJourney::new(now);
         let mut new_journey_2 = IncompleteWorkJourney::new(now_plus_seven);
@@ -110,15 +407,16 @@ pub mod tests {
         let journey2 = new_journey_2.end(now_plus_eight).unwrap();
 
         let journeys = vec![journey, journey2];
-        let _work_day = WorkDay::new(&journeys);
+        let work_day = WorkDay::new(&journeys, &DayPolicy::default());
+        assert!(work_day.is_ok());
     }
 
     #[test]
     fn basic_work_day_math() {
         let (now, now_plus_eight, work_day) = initialize_mock_day();
 
-        assert_eq!(now, work_day.first_clock_in());
-        assert_eq!(now_plus_eight, work_day.last_clock_out());
+        assert_eq!(Some(now), work_day.first_clock_in());
+        assert_eq!(Some(now_plus_eight), work_day.last_clock_out());
         assert_eq!(TimeDelta::hours(7).num_seconds(), work_day.worked_hours());
     }
 
@@ -126,20 +424,33 @@ pub mod tests {
     fn missing_hours_violation_check() {
         let (_now, _now_plus_eightt, work_day) = initialize_missing_hours_violated_mock_day();
 
-        assert!(work_day
-            .violations
-            .contains(&crate::work_days::IntraDayViolation::MissingHours));
+        assert!(work_day.has_violation(ViolationKind::MissingHours));
 
         assert_eq!(TimeDelta::hours(5).num_seconds(), work_day.worked_hours());
     }
 
+    #[test]
+    fn violations_stored_under_the_old_key_are_migrated() {
+        let (_now, _now_plus_eight, work_day) = initialize_missing_hours_violated_mock_day();
+        let mut legacy_day = serde_json::to_value(&work_day).unwrap();
+        let legacy_object = legacy_day.as_object_mut().unwrap();
+        legacy_object.remove("violation_records");
+        legacy_object.insert(
+            "violations".to_string(),
+            serde_json::json!(["MissingHours"]),
+        );
+
+        let migrated: WorkDay = serde_json::from_value(legacy_day).unwrap();
+
+        assert!(migrated.has_violation(ViolationKind::MissingHours));
+        assert!(!migrated.has_violation(ViolationKind::ExceededMaxHours));
+    }
+
     #[test]
     fn inter_journey_violation_check() {
         let (_now, _now_plus_eight, work_day) = inter_journey_violated_mock_day();
 
-        assert!(work_day
-            .violations
-            .contains(&crate::work_days::IntraDayViolation::ViolatedInterJourneyRest));
+        assert!(work_day.has_violation(ViolationKind::ViolatedInterJourneyRest));
 
         assert_eq!(TimeDelta::hours(7).num_seconds(), work_day.worked_hours());
     }
@@ -148,15 +459,240 @@ pub mod tests {
     fn excedded_hours_violation_check() {
         let (_now, _now_plus_eight, work_day) = excedded_hours_violated_mock_day();
 
-        assert!(work_day
-            .violations
-            .contains(&crate::work_days::IntraDayViolation::ExceddedMaxHours));
+        assert!(work_day.has_violation(ViolationKind::ExceededMaxHours));
 
         assert_eq!(TimeDelta::hours(11).num_seconds(), work_day.worked_hours());
     }
 
+    #[test]
+    fn journey_length_violation_check() {
+        let start = Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap();
+        let journey = WorkJourney::new(start, start + TimeDelta::hours(9)).unwrap();
+
+        let work_day = WorkDay::new(&[journey], &DayPolicy::default()).unwrap();
+        let violation = work_day
+            .get_violations()
+            .into_iter()
+            .find(|violation| violation.kind == ViolationKind::ExceededMaxJourneyLength)
+            .unwrap();
+
+        assert_eq!(vec![0], violation.journeys);
+        assert_eq!(
+            ViolationMeasure::Seconds(TimeDelta::hours(9).num_seconds()),
+            violation.measured
+        );
+        assert!(!work_day.has_violation(ViolationKind::ExceededMaxHours));
+    }
+
+    #[test]
+    fn open_journey_length_violation_check() {
+        let start = Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap();
+        let open_journey = IncompleteWorkJourney::new(start);
+
+        assert!(journey_length_violation(
+            start,
+            open_journey.elapsed(start + TimeDelta::hours(6)),
+            &DayPolicy::default()
+        )
+        .is_none());
+        assert!(journey_length_violation(
+            start,
+            open_journey.elapsed(start + TimeDelta::hours(7)),
+            &DayPolicy::default()
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn short_day_break_boundaries() {
+        let four_hours_no_break = initialize_fixed_day(&[(0, 240)]);
+        let over_four_hours_no_break = initialize_fixed_day(&[(0, 241)]);
+        let six_hours_with_break = initialize_fixed_day(&[(0, 180), (195, 180)]);
+        let six_hours_with_short_break = initialize_fixed_day(&[(0, 180), (194, 180)]);
+
+        assert!(!four_hours_no_break.has_violation(ViolationKind::MissingShortDayBreak));
+        assert!(over_four_hours_no_break.has_violation(ViolationKind::MissingShortDayBreak));
+        assert!(!six_hours_with_break.has_violation(ViolationKind::MissingShortDayBreak));
+        assert!(!six_hours_with_break.has_violation(ViolationKind::ViolatedInterJourneyRest));
+        assert!(six_hours_with_short_break.has_violation(ViolationKind::MissingShortDayBreak));
+    }
+
+    #[test]
+    fn long_day_break_boundaries() {
+        let with_break = initialize_fixed_day(&[(0, 240), (300, 181)]);
+        let with_short_break = initialize_fixed_day(&[(0, 240), (299, 181)]);
+
+        assert!(!with_break.has_violation(ViolationKind::ViolatedInterJourneyRest));
+        assert!(!with_break.has_violation(ViolationKind::MissingShortDayBreak));
+        assert!(with_short_break.has_violation(ViolationKind::ViolatedInterJourneyRest));
+    }
+
+    #[test]
+    fn max_break_boundaries() {
+        let two_hours_break = initialize_fixed_day(&[(0, 240), (360, 240)]);
+        let over_two_hours_break = initialize_fixed_day(&[(0, 240), (361, 240)]);
+
+        assert!(!two_hours_break.has_violation(ViolationKind::ExceededMaxBreak));
+        assert!(over_two_hours_break.has_violation(ViolationKind::ExceededMaxBreak));
+        assert_eq!(
+            vec![0, 1],
+            over_two_hours_break
+                .get_violations()
+                .into_iter()
+                .find(|violation| violation.kind == ViolationKind::ExceededMaxBreak)
+                .unwrap()
+                .journeys
+        );
+    }
+
+    #[test]
+    fn breaks_below_threshold_are_not_rest() {
+        let counted_breaks = initialize_fixed_day(&[(0, 180), (235, 120), (365, 120)]);
+        let uncounted_break = initialize_fixed_day(&[(0, 180), (235, 120), (364, 120)]);
+
+        assert!(!counted_breaks.has_violation(ViolationKind::ViolatedInterJourneyRest));
+        assert!(uncounted_break.has_violation(ViolationKind::ViolatedInterJourneyRest));
+    }
+
+    #[test]
+    fn deviations_within_tolerance_are_not_counted() {
+        let policy = DayPolicy {
+            clock_time: ClockTimePolicy {
+                punch_tolerance: TimeDelta::minutes(5),
+                daily_tolerance: TimeDelta::minutes(10),
+                scheduled_punches: [8, 12, 13, 17]
+                    .into_iter()
+                    .map(|hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap())
+                    .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let within_tolerance = initialize_fixed_day_with(&[(3, 237), (302, 240)], &policy);
+        let over_punch_tolerance = initialize_fixed_day_with(&[(6, 234), (300, 240)], &policy);
+        let over_daily_tolerance = initialize_fixed_day_with(&[(4, 236), (304, 240)], &policy);
+        let off_schedule = initialize_fixed_day_with(&[(0, 10), (80, 400)], &policy);
+        let without_tolerance = initialize_fixed_day(&[(3, 237), (302, 240)]);
+
+        assert_eq!(
+            TimeDelta::hours(8).num_seconds(),
+            within_tolerance.worked_hours()
+        );
+        assert_eq!(
+            TimeDelta::minutes(477).num_seconds(),
+            within_tolerance.raw_worked_hours()
+        );
+        assert_eq!(
+            TimeDelta::minutes(474).num_seconds(),
+            over_punch_tolerance.worked_hours()
+        );
+        assert_eq!(
+            TimeDelta::minutes(476).num_seconds(),
+            over_daily_tolerance.worked_hours()
+        );
+        assert_eq!(
+            TimeDelta::minutes(410).num_seconds(),
+            off_schedule.worked_hours()
+        );
+        assert_eq!(
+            TimeDelta::minutes(477).num_seconds(),
+            without_tolerance.worked_hours()
+        );
+    }
+
+    #[test]
+    fn punches_are_rounded_to_the_configured_step() {
+        let policy = DayPolicy {
+            clock_time: ClockTimePolicy {
+                rounding_step: TimeDelta::minutes(15),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let start = mock_day_start();
+        let journeys = [
+            WorkJourney::new(
+                start + TimeDelta::minutes(7),
+                start + TimeDelta::minutes(248),
+            )
+            .unwrap(),
+            WorkJourney::new(
+                start + TimeDelta::minutes(308),
+                start + TimeDelta::minutes(512),
+            )
+            .unwrap(),
+        ];
+
+        let work_day = WorkDay::new(&journeys, &policy).unwrap();
+
+        assert_eq!(
+            TimeDelta::minutes(255 + 195).num_seconds(),
+            work_day.worked_hours()
+        );
+        assert_eq!(
+            Some(start + TimeDelta::minutes(7)),
+            work_day.first_clock_in()
+        );
+    }
+
+    /// Builds a day from `(start, length)` pairs in minutes after 08:00.
+    fn initialize_fixed_day(journeys: &[(i64, i64)]) -> WorkDay {
+        initialize_fixed_day_with(journeys, &DayPolicy::default())
+    }
+
+    fn initialize_fixed_day_with(journeys: &[(i64, i64)], policy: &DayPolicy) -> WorkDay {
+        let day_start = Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap();
+        let journeys: Vec<WorkJourney> = journeys
+            .iter()
+            .map(|(start, length)| {
+                let starting_time = day_start + TimeDelta::minutes(*start);
+                WorkJourney::new(starting_time, starting_time + TimeDelta::minutes(*length))
+                    .unwrap()
+            })
+            .collect();
+
+        WorkDay::new(&journeys, policy).unwrap()
+    }
+
+    #[test]
+    fn invalid_work_day_initialization() {
+        let start = mock_day_start();
+        let first = WorkJourney::new(start, start + TimeDelta::hours(4)).unwrap();
+        let overlapping =
+            WorkJourney::new(start + TimeDelta::hours(3), start + TimeDelta::hours(5)).unwrap();
+        let earlier =
+            WorkJourney::new(start - TimeDelta::hours(3), start - TimeDelta::hours(2)).unwrap();
+        let next_day = WorkJourney::new(
+            start + TimeDelta::days(1),
+            start + TimeDelta::days(1) + TimeDelta::hours(2),
+        )
+        .unwrap();
+        let policy = DayPolicy::default();
+
+        assert_eq!(
+            WorkDayError::EmptyDay,
+            WorkDay::new(&[], &policy).unwrap_err()
+        );
+        assert_eq!(
+            WorkDayError::OverlappingJourneys(1),
+            WorkDay::new(&[first.clone(), overlapping], &policy).unwrap_err()
+        );
+        assert_eq!(
+            WorkDayError::UnorderedJourneys(1),
+            WorkDay::new(&[first.clone(), earlier], &policy).unwrap_err()
+        );
+        assert_eq!(
+            WorkDayError::JourneysSpanDifferentDays(1),
+            WorkDay::new(&[first, next_day], &policy).unwrap_err()
+        );
+    }
+
+    pub fn mock_day_start() -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap()
+    }
+
     pub fn initialize_mock_day() -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>, WorkDay) {
-        let now = Utc::now();
+        let now = mock_day_start();
         let now_plus_six = now + TimeDelta::hours(6);
         let now_plus_seven = now_plus_six + TimeDelta::hours(1);
         let now_plus_eight = now_plus_seven + TimeDelta::hours(1);
@@ -167,13 +703,13 @@ pub mod tests {
         let journey2 = new_journey_2.end(now_plus_eight).unwrap();
 
         let journeys = vec![journey, journey2];
-        let work_day = WorkDay::new(&journeys);
+        let work_day = WorkDay::new(&journeys, &DayPolicy::default()).unwrap();
         (now, now_plus_eight, work_day)
     }
 
     fn initialize_missing_hours_violated_mock_day(
     ) -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>, WorkDay) {
-        let now = Utc::now();
+        let now = mock_day_start();
         let now_plus_four = now + TimeDelta::hours(4);
         let now_plus_four_and_a_half = now_plus_four + TimeDelta::minutes(30);
         let now_plus_five_and_a_half = now_plus_four_and_a_half + TimeDelta::hours(1);
@@ -184,13 +720,13 @@ pub mod tests {
         let journey2 = new_journey_2.end(now_plus_five_and_a_half).unwrap();
 
         let journeys = vec![journey, journey2];
-        let work_day = WorkDay::new(&journeys);
+        let work_day = WorkDay::new(&journeys, &DayPolicy::default()).unwrap();
         (now, now_plus_five_and_a_half, work_day)
     }
 
     fn excedded_hours_violated_mock_day() -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>, WorkDay)
     {
-        let now = Utc::now();
+        let now = mock_day_start();
         let now_plus_six = now + TimeDelta::hours(6);
         let now_plus_seven = now_plus_six + TimeDelta::hours(1);
         let now_plus_twelve = now_plus_seven + TimeDelta::hours(5);
@@ -201,13 +737,13 @@ pub mod tests {
         let journey2 = new_journey_2.end(now_plus_twelve).unwrap();
 
         let journeys = vec![journey, journey2];
-        let work_day = WorkDay::new(&journeys);
+        let work_day = WorkDay::new(&journeys, &DayPolicy::default()).unwrap();
         (now, now_plus_twelve, work_day)
     }
 
     fn inter_journey_violated_mock_day() -> (chrono::DateTime<Utc>, chrono::DateTime<Utc>, WorkDay)
     {
-        let now = Utc::now();
+        let now = mock_day_start();
         let now_plus_six = now + TimeDelta::hours(6);
         let now_plus_six_and_a_half = now_plus_six + TimeDelta::minutes(30);
         let now_plus_seven_and_a_half = now_plus_six_and_a_half + TimeDelta::hours(1);
@@ -218,7 +754,7 @@ pub mod tests {
         let journey2 = new_journey_2.end(now_plus_seven_and_a_half).unwrap();
 
         let journeys = vec![journey, journey2];
-        let work_day = WorkDay::new(&journeys);
+        let work_day = WorkDay::new(&journeys, &DayPolicy::default()).unwrap();
         (now, now_plus_seven_and_a_half, work_day)
     }
 }
@@ -20,6 +20,10 @@ impl IncompleteWorkJourney {
         }
     }
 
+    pub fn elapsed(&self, current_time: DateTime<Utc>) -> TimeDelta {
+        current_time - self.starting_time
+    }
+
     pub fn end(&mut self, current_time: DateTime<Utc>) -> Result<WorkJourney, WorkJourneyError> {
         WorkJourney::new(self.starting_time, current_time)
     }
@@ -29,6 +33,8 @@ impl IncompleteWorkJourney {
 pub struct WorkJourney {
     starting_time: DateTime<Utc>,
     ending_time: DateTime<Utc>,
+    #[serde(default)]
+    corrected: bool,
 }
 
 impl WorkJourney {
@@ -40,6 +46,7 @@ impl WorkJourney {
             Ok(WorkJourney {
                 starting_time,
                 ending_time,
+                corrected: false,
             })
         } else {
             Err(WorkJourneyError::InvalidClockBoundaries(
@@ -61,6 +68,16 @@ impl WorkJourney {
         self.ending_time
     }
 
+    /// Marks a journey whose boundaries were not punched by the user, e.g. a
+    /// forgotten clock-out closed afterwards.
+    pub fn mark_corrected(&mut self) {
+        self.corrected = true;
+    }
+
+    pub fn is_corrected(&self) -> bool {
+        self.corrected
+    }
+
     fn validate(starting_time: &DateTime<Utc>, ending_time: &DateTime<Utc>) -> bool {
         ending_time >= starting_time
     }
@@ -68,28 +85,32 @@ impl WorkJourney {
 
 #[cfg(test)]
 mod tests {
-    use std::{thread::sleep, time::Duration};
+    use chrono::{TimeDelta, TimeZone, Utc};
 
-    use chrono::{TimeDelta, Utc};
+    use crate::{
+        clock::{Clock, FixedClock},
+        work_journey::IncompleteWorkJourney,
+    };
 
-    use crate::work_journey::IncompleteWorkJourney;
+    fn mock_clock() -> FixedClock {
+        FixedClock::new(Utc.with_ymd_and_hms(2024, 7, 10, 8, 0, 0).unwrap())
+    }
 
     #[test]
     fn basic_work_journey_initialization() {
-        let now = Utc::now();
+        let now = mock_clock().now();
 
         let mut new_journey = IncompleteWorkJourney::new(now);
 
-        let now_2 = Utc::now();
+        let now_2 = now + TimeDelta::seconds(1);
         let journey = new_journey.end(now_2);
         assert!(journey.is_ok())
     }
 
     #[test]
     fn incorrect_work_journey_initialization() {
-        let now = Utc::now();
-        sleep(Duration::from_secs(1));
-        let now_2 = Utc::now();
+        let now = mock_clock().now();
+        let now_2 = now + TimeDelta::seconds(1);
 
         let mut new_journey = IncompleteWorkJourney::new(now_2);
 
@@ -99,7 +120,7 @@ mod tests {
 
     #[test]
     fn basic_work_journey_math() {
-        let now = Utc::now();
+        let now = mock_clock().now();
         let now_plus_6 = now + TimeDelta::hours(6);
 
         let mut new_journey = IncompleteWorkJourney::new(now);
@@ -1,55 +1,170 @@
 use std::num::TryFromIntError;
 
-use chrono::{DateTime, TimeDelta, Utc};
-use colored::Colorize;
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 
-use crate::{delta_hours::DeltaHours, work_days::WorkDay};
+use crate::{
+    delta_hours::DeltaHours,
+    policy::{PolicySchedule, WeekPolicy},
+    premium::PremiumBuckets,
+    violations::{Violation, ViolationKind, ViolationMeasure},
+    work_days::WorkDay,
+};
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
-pub enum InterDayViolation {
-    InterDayRestViolation,
-}
+pub const MAX_DAYS_PER_WEEK: usize = 7;
+pub const MIN_INTER_DAY_REST_TIME_DELTA: TimeDelta = TimeDelta::hours(11);
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "StoredWorkWeek")]
 pub struct WorkWeek {
     pub workdays: Vec<WorkDay>,
-    violation: Option<InterDayViolation>,
+    #[serde(rename = "violation_records", default)]
+    violations: Vec<Violation>,
 }
 
 impl WorkWeek {
     pub fn new() -> WorkWeek {
-        let workdays: Vec<WorkDay> = Vec::with_capacity(5);
-        let violations = None;
+        let workdays: Vec<WorkDay> = Vec::with_capacity(MAX_DAYS_PER_WEEK);
+        let violations = Vec::new();
 
         WorkWeek {
             workdays,
-            violation: violations,
+            violations,
         }
     }
 
-    pub fn append_day(&mut self, day: &WorkDay) {
-        if self.workdays.len() < 5 {
-            self.workdays.push(day.clone())
+    pub fn append_day(&mut self, day: &WorkDay, policy: &WeekPolicy) {
+        if self.workdays.len() >= MAX_DAYS_PER_WEEK {
+            return;
+        }
+
+        if let Some(violation) = self
+            .workdays
+            .last()
+            .and_then(|previous_day| inter_day_rest_violation(previous_day, day, policy))
+        {
+            self.violations.push(violation);
         }
 
-        for (index, day) in self.workdays.iter().enumerate() {
-            if let Some(next_day) = self.workdays.get(index + 1) {
-                let next_day_first_clock_in = next_day.first_clock_in();
-                let this_day_first_clock_in = day.last_clock_out();
+        self.workdays.push(day.clone());
+    }
+
+    /// Evaluates the rules that need the whole week, using the previous weeks
+    /// as context for rest, consecutive workdays and Sunday frequency.
+    pub fn evaluate_weekly_rules(&mut self, previous_weeks: &[WorkWeek], policy: &WeekPolicy) {
+        let (previous_weekly_violations, violations): (Vec<Violation>, Vec<Violation>) = self
+            .violations
+            .drain(..)
+            .partition(|violation| violation.kind.is_weekly());
+        self.violations = violations;
+
+        let week_dates: Vec<NaiveDate> = self.workdays.iter().filter_map(WorkDay::date).collect();
+        let (Some(&first_date), Some(&last_date)) = (week_dates.first(), week_dates.last()) else {
+            return;
+        };
+        let mut weekly_violations = Vec::new();
+
+        if self.worked_hours() > policy.max_weekly_hours.num_seconds() {
+            weekly_violations.push(
+                Violation::new(
+                    ViolationKind::ExceededMaxWeeklyHours,
+                    last_date,
+                    ViolationMeasure::Seconds(self.worked_hours()),
+                    ViolationMeasure::Seconds(policy.max_weekly_hours.num_seconds()),
+                )
+                .with_days(week_dates.clone()),
+            );
+        }
 
-                if next_day_first_clock_in - this_day_first_clock_in < TimeDelta::hours(11) {
-                    println!(
-                        "{}",
-                        "Inter-day rest was violated!"
-                            .red()
-                            .on_bright_white()
-                            .bold()
+        if policy.max_average_weekly_hours > TimeDelta::zero()
+            && previous_weeks.len() + 1 >= policy.reference_period_weeks
+        {
+            let reference_weeks = previous_weeks
+                .iter()
+                .rev()
+                .take(policy.reference_period_weeks.saturating_sub(1))
+                .chain(std::iter::once(&*self));
+            if let Some(average) = average_weekly_hours(reference_weeks) {
+                if average > policy.max_average_weekly_hours.num_seconds() {
+                    weekly_violations.push(
+                        Violation::new(
+                            ViolationKind::ExceededAverageWeeklyHours,
+                            last_date,
+                            ViolationMeasure::Seconds(average),
+                            ViolationMeasure::Seconds(
+                                policy.max_average_weekly_hours.num_seconds(),
+                            ),
+                        )
+                        .with_days(week_dates.clone()),
                     );
-                    self.violation = Some(InterDayViolation::InterDayRestViolation);
                 }
             }
         }
+
+        if let Some(longest_rest) = self.longest_rest(previous_weeks) {
+            if longest_rest < policy.min_weekly_rest {
+                weekly_violations.push(
+                    Violation::new(
+                        ViolationKind::MissingWeeklyRest,
+                        first_date,
+                        ViolationMeasure::Seconds(longest_rest.num_seconds()),
+                        ViolationMeasure::Seconds(policy.min_weekly_rest.num_seconds()),
+                    )
+                    .with_days(week_dates.clone()),
+                );
+            }
+        }
+
+        let consecutive_workdays = self.longest_consecutive_workdays(previous_weeks);
+        if consecutive_workdays.len() > policy.max_consecutive_workdays {
+            weekly_violations.push(
+                Violation::new(
+                    ViolationKind::ExceededMaxConsecutiveWorkdays,
+                    *consecutive_workdays.last().unwrap_or(&last_date),
+                    ViolationMeasure::Count(consecutive_workdays.len()),
+                    ViolationMeasure::Count(policy.max_consecutive_workdays),
+                )
+                .with_days(consecutive_workdays),
+            );
+        }
+
+        if let Some(sunday) = self.worked_sunday() {
+            let consecutive_sundays = 1 + previous_weeks
+                .iter()
+                .rev()
+                .take_while(|week| week.worked_sunday().is_some())
+                .count();
+
+            if consecutive_sundays > policy.max_consecutive_sundays {
+                weekly_violations.push(
+                    Violation::new(
+                        ViolationKind::ExceededSundayWorkFrequency,
+                        sunday,
+                        ViolationMeasure::Count(consecutive_sundays),
+                        ViolationMeasure::Count(policy.max_consecutive_sundays),
+                    )
+                    .with_days(vec![sunday]),
+                );
+            }
+        }
+
+        for violation in weekly_violations.iter_mut() {
+            if let Some(previous) = previous_weekly_violations
+                .iter()
+                .find(|previous| previous.is_same_occurrence(violation))
+            {
+                violation.justification = previous.justification.clone();
+                violation.acknowledged = previous.acknowledged;
+            }
+        }
+        self.violations.extend(weekly_violations);
+    }
+
+    pub fn worked_sunday(&self) -> Option<NaiveDate> {
+        self.workdays
+            .iter()
+            .filter_map(WorkDay::date)
+            .find(|date| date.weekday() == Weekday::Sun)
     }
 
     pub fn worked_hours(&self) -> i64 {
@@ -60,25 +175,138 @@ impl WorkWeek {
     }
 
     pub fn worked_delta(&self) -> Result<DeltaHours, TryFromIntError> {
-        let current_delta_time = self.expected_hours()? - self.worked_hours();
+        let current_delta_time = self.expected_hours() - self.worked_hours();
         Ok(DeltaHours::new(current_delta_time))
     }
 
+    /// Date of the last day with journeys.
+    pub fn last_date(&self) -> Option<NaiveDate> {
+        self.workdays.iter().rev().find_map(WorkDay::date)
+    }
+
     pub fn last_clock_out_last_day_in_week(&self) -> Option<DateTime<Utc>> {
-        self.workdays.last().map(|item| item.last_clock_out())
+        self.workdays.iter().rev().find_map(WorkDay::last_clock_out)
+    }
+
+    pub fn premium_buckets(&self, policy: &PolicySchedule) -> PremiumBuckets {
+        let mut buckets = self
+            .workdays
+            .iter()
+            .filter_map(|item| Some((item, item.date()?)))
+            .fold(PremiumBuckets::default(), |mut acc, (item, date)| {
+                acc += item.premium_buckets(policy.policy_at(date));
+                acc
+            });
+        buckets.apply_weekly_overtime(
+            policy
+                .policy_for_week(self)
+                .premium
+                .weekly_overtime_threshold,
+        );
+        buckets
     }
 
-    pub fn get_violation(&self) -> Option<InterDayViolation> {
-        self.violation
+    pub fn get_violations(&self) -> Vec<Violation> {
+        self.violations.clone()
     }
 
-    fn days_worked(&self) -> usize {
-        self.workdays.len()
+    /// Attaches the justification to the violations of `kind` on `date`, on
+    /// the week itself or on its days, returning how many were justified.
+    pub fn justify_violation(
+        &mut self,
+        date: NaiveDate,
+        kind: ViolationKind,
+        justification: &str,
+        acknowledged: bool,
+    ) -> usize {
+        let mut justified = 0;
+        for violation in self
+            .violations
+            .iter_mut()
+            .filter(|item| item.kind == kind && item.date == date)
+        {
+            violation.justify(justification.to_string(), acknowledged);
+            justified += 1;
+        }
+
+        for day in self
+            .workdays
+            .iter_mut()
+            .filter(|item| item.date() == Some(date))
+        {
+            justified += day.justify_violation(kind, justification, acknowledged);
+        }
+        justified
     }
 
-    fn expected_hours(&self) -> Result<i64, TryFromIntError> {
-        let hours = (self.days_worked() * 8).try_into()?;
-        Ok(TimeDelta::hours(hours).num_seconds())
+    pub fn has_violation(&self, kind: ViolationKind) -> bool {
+        self.violations
+            .iter()
+            .any(|violation| violation.kind == kind)
+    }
+
+    /// Longest rest inside the week, including the rest since the previous
+    /// week. `None` without a previous week, as the rest before the first
+    /// recorded week is unknown.
+    fn longest_rest(&self, previous_weeks: &[WorkWeek]) -> Option<TimeDelta> {
+        let mut last_clock_out = previous_weeks.last()?.last_clock_out_last_day_in_week()?;
+        let mut longest_rest = None;
+
+        for day in &self.workdays {
+            let (Some(first_clock_in), Some(day_last_clock_out)) =
+                (day.first_clock_in(), day.last_clock_out())
+            else {
+                continue;
+            };
+            longest_rest = longest_rest.max(Some(first_clock_in - last_clock_out));
+            last_clock_out = day_last_clock_out;
+        }
+
+        longest_rest
+    }
+
+    /// Longest run of calendar-consecutive workdays reaching into this week.
+    fn longest_consecutive_workdays(&self, previous_weeks: &[WorkWeek]) -> Vec<NaiveDate> {
+        let previous_dates = previous_weeks
+            .iter()
+            .flat_map(|week| week.workdays.iter())
+            .filter_map(|day| Some((day.date()?, false)));
+        let this_week_dates = self
+            .workdays
+            .iter()
+            .filter_map(|day| Some((day.date()?, true)));
+
+        let mut longest_run = Vec::new();
+        let mut current_run: Vec<NaiveDate> = Vec::new();
+        for (date, in_this_week) in previous_dates.chain(this_week_dates) {
+            match current_run.last() {
+                Some(last) if date - *last == TimeDelta::days(1) => current_run.push(date),
+                Some(last) if date == *last => (),
+                _ => current_run = vec![date],
+            }
+            if in_this_week && current_run.len() > longest_run.len() {
+                longest_run = current_run.clone();
+            }
+        }
+        longest_run
+    }
+
+    /// Expected seconds of the worked days, each under its own policy.
+    fn expected_hours(&self) -> i64 {
+        self.workdays.iter().map(WorkDay::expected_hours).sum()
+    }
+}
+
+/// Average worked seconds per week, `None` without weeks.
+pub fn average_weekly_hours<'a>(weeks: impl IntoIterator<Item = &'a WorkWeek>) -> Option<i64> {
+    let (total, count) = weeks.into_iter().fold((0, 0), |(total, count), week| {
+        (total + week.worked_hours(), count + 1)
+    });
+
+    if count == 0 {
+        None
+    } else {
+        Some(total / count)
     }
 }
 
@@ -88,11 +316,74 @@ impl Default for WorkWeek {
     }
 }
 
+fn inter_day_rest_violation(
+    previous_day: &WorkDay,
+    day: &WorkDay,
+    policy: &WeekPolicy,
+) -> Option<Violation> {
+    let (first_clock_in, last_clock_out) = (day.first_clock_in()?, previous_day.last_clock_out()?);
+    let inter_day_rest = first_clock_in - last_clock_out;
+    (inter_day_rest < policy.min_inter_day_rest).then(|| {
+        Violation::new(
+            ViolationKind::InterDayRest,
+            first_clock_in.date_naive(),
+            ViolationMeasure::Seconds(inter_day_rest.num_seconds()),
+            ViolationMeasure::Seconds(policy.min_inter_day_rest.num_seconds()),
+        )
+        .with_days(vec![
+            last_clock_out.date_naive(),
+            first_clock_in.date_naive(),
+        ])
+    })
+}
+
+/// Stored form of a `WorkWeek`, also reading the inter-day rest flag kept by
+/// older registries under `violation`.
+#[derive(Deserialize)]
+struct StoredWorkWeek {
+    workdays: Vec<WorkDay>,
+    #[serde(rename = "violation_records", default)]
+    violations: Vec<Violation>,
+    #[serde(rename = "violation", default)]
+    legacy_violation: Option<LegacyWeekViolation>,
+}
+
+#[derive(Deserialize)]
+enum LegacyWeekViolation {
+    InterDayRestViolation,
+}
+
+impl From<StoredWorkWeek> for WorkWeek {
+    fn from(stored: StoredWorkWeek) -> Self {
+        let mut violations = stored.violations;
+        if violations.is_empty() && stored.legacy_violation.is_some() {
+            let policy = WeekPolicy::default();
+            violations = stored
+                .workdays
+                .windows(2)
+                .filter_map(|pair| inter_day_rest_violation(&pair[0], &pair[1], &policy))
+                .collect();
+        }
+
+        WorkWeek {
+            workdays: stored.workdays,
+            violations,
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
-    use chrono::TimeDelta;
+    use chrono::{NaiveDate, TimeDelta, TimeZone, Utc};
 
-    use crate::{delta_hours::DeltaHours, work_days::tests::initialize_mock_day};
+    use crate::{
+        delta_hours::DeltaHours,
+        policy::{DayPolicy, WeekPolicy},
+        violations::{ViolationKind, ViolationMeasure},
+        work_days::tests::initialize_mock_day,
+        work_days::WorkDay,
+        work_journey::WorkJourney,
+    };
 
     use super::WorkWeek;
 
@@ -111,7 +402,8 @@ pub mod tests {
             mock_week.worked_hours()
         );
         assert!(
-            work_day_five.last_clock_out() - mock_week.last_clock_out_last_day_in_week().unwrap()
+            work_day_five.last_clock_out().unwrap()
+                - mock_week.last_clock_out_last_day_in_week().unwrap()
                 < TimeDelta::seconds(1)
         );
         assert_eq!(
@@ -124,7 +416,186 @@ pub mod tests {
     fn missing_hours_violation_check() {
         let mock_week = intialize_mock_week();
 
-        assert!(mock_week.violation.is_some());
+        assert!(mock_week.has_violation(ViolationKind::InterDayRest));
+    }
+
+    #[test]
+    fn every_inter_day_rest_violation_is_kept() {
+        let week = initialize_fixed_week(2024, 7, &[8, 9, 10, 11], 14);
+
+        let rest_violations: Vec<_> = week
+            .get_violations()
+            .into_iter()
+            .filter(|violation| violation.kind == ViolationKind::InterDayRest)
+            .collect();
+
+        assert_eq!(3, rest_violations.len());
+        assert_eq!(
+            ViolationMeasure::Seconds(TimeDelta::hours(10).num_seconds()),
+            rest_violations[0].measured
+        );
+        assert_eq!(
+            ViolationMeasure::Seconds(TimeDelta::hours(11).num_seconds()),
+            rest_violations[0].limit
+        );
+        assert_eq!(
+            vec![
+                NaiveDate::from_ymd_opt(2024, 7, 8).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 7, 9).unwrap()
+            ],
+            rest_violations[0].days
+        );
+    }
+
+    #[test]
+    fn max_weekly_hours_violation_check() {
+        let mut week = initialize_fixed_week(2024, 7, &[8, 9, 10, 11, 12], 9);
+
+        week.evaluate_weekly_rules(&[], &WeekPolicy::default());
+
+        assert!(week.has_violation(ViolationKind::ExceededMaxWeeklyHours));
+    }
+
+    #[test]
+    fn justifications_survive_weekly_re_evaluation() {
+        let mut week = initialize_fixed_week(2024, 7, &[8, 9, 10, 11, 12], 9);
+        let last_date = NaiveDate::from_ymd_opt(2024, 7, 12).unwrap();
+        week.evaluate_weekly_rules(&[], &WeekPolicy::default());
+
+        let justified = week.justify_violation(
+            last_date,
+            ViolationKind::ExceededMaxWeeklyHours,
+            "Release week",
+            true,
+        );
+        week.evaluate_weekly_rules(&[], &WeekPolicy::default());
+
+        assert_eq!(1, justified);
+        let violation = week
+            .get_violations()
+            .into_iter()
+            .find(|violation| violation.kind == ViolationKind::ExceededMaxWeeklyHours)
+            .unwrap();
+        assert_eq!(Some("Release week".to_string()), violation.justification);
+        assert!(violation.acknowledged);
+        assert_eq!(
+            1,
+            week.justify_violation(
+                last_date,
+                ViolationKind::ExceededMaxJourneyLength,
+                "",
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn weekly_justifications_survive_appended_days() {
+        let mut week = initialize_fixed_week(2024, 7, &[8, 9, 10, 11, 12], 9);
+        week.evaluate_weekly_rules(&[], &WeekPolicy::default());
+        week.justify_violation(
+            NaiveDate::from_ymd_opt(2024, 7, 12).unwrap(),
+            ViolationKind::ExceededMaxWeeklyHours,
+            "Release week",
+            true,
+        );
+
+        let saturday = initialize_fixed_week(2024, 7, &[13], 4);
+        week.append_day(&saturday.workdays[0], &WeekPolicy::default());
+        week.evaluate_weekly_rules(&[], &WeekPolicy::default());
+
+        let violation = week
+            .get_violations()
+            .into_iter()
+            .find(|violation| violation.kind == ViolationKind::ExceededMaxWeeklyHours)
+            .unwrap();
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2024, 7, 13).unwrap(),
+            violation.date
+        );
+        assert_eq!(Some("Release week".to_string()), violation.justification);
+        assert!(violation.acknowledged);
+    }
+
+    #[test]
+    fn weekly_rest_violation_check() {
+        let previous_week = initialize_fixed_week(2024, 7, &[5, 6], 8);
+        let rested_previous_week = initialize_fixed_week(2024, 7, &[4, 5], 8);
+        let mut week = initialize_fixed_week(2024, 7, &[7, 8, 9], 8);
+
+        week.evaluate_weekly_rules(&[previous_week], &WeekPolicy::default());
+        assert!(week.has_violation(ViolationKind::MissingWeeklyRest));
+
+        week.evaluate_weekly_rules(&[rested_previous_week], &WeekPolicy::default());
+        assert!(!week.has_violation(ViolationKind::MissingWeeklyRest));
+
+        week.evaluate_weekly_rules(&[], &WeekPolicy::default());
+        assert!(!week.has_violation(ViolationKind::MissingWeeklyRest));
+    }
+
+    #[test]
+    fn inter_day_rest_stored_under_the_old_key_is_migrated() {
+        let mut week = WorkWeek::new();
+        for (start, end) in [(8, 20), (30, 38)] {
+            let journey = WorkJourney::new(
+                Utc.with_ymd_and_hms(2024, 7, 8, 0, 0, 0).unwrap() + TimeDelta::hours(start),
+                Utc.with_ymd_and_hms(2024, 7, 8, 0, 0, 0).unwrap() + TimeDelta::hours(end),
+            )
+            .unwrap();
+            week.append_day(
+                &WorkDay::new(&[journey], &DayPolicy::default()).unwrap(),
+                &WeekPolicy::default(),
+            );
+        }
+        let mut legacy_week = serde_json::to_value(&week).unwrap();
+        let legacy_object = legacy_week.as_object_mut().unwrap();
+        legacy_object.remove("violation_records");
+        legacy_object.insert(
+            "violation".to_string(),
+            serde_json::json!("InterDayRestViolation"),
+        );
+
+        let migrated: WorkWeek = serde_json::from_value(legacy_week).unwrap();
+
+        assert!(week.has_violation(ViolationKind::InterDayRest));
+        assert!(migrated.has_violation(ViolationKind::InterDayRest));
+    }
+
+    #[test]
+    fn consecutive_workdays_violation_check() {
+        let previous_week = initialize_fixed_week(2024, 7, &[1, 2, 3, 4, 5], 8);
+        let mut week = initialize_fixed_week(2024, 7, &[6, 7], 4);
+
+        week.evaluate_weekly_rules(&[previous_week], &WeekPolicy::default());
+
+        assert!(week.has_violation(ViolationKind::ExceededMaxConsecutiveWorkdays));
+    }
+
+    #[test]
+    fn sunday_work_frequency_violation_check() {
+        let previous_week = initialize_fixed_week(2024, 7, &[7], 4);
+        let mut week = initialize_fixed_week(2024, 7, &[14], 4);
+        let policy = WeekPolicy {
+            max_consecutive_sundays: 1,
+            ..Default::default()
+        };
+
+        week.evaluate_weekly_rules(&[previous_week], &policy);
+
+        assert!(week.has_violation(ViolationKind::ExceededSundayWorkFrequency));
+    }
+
+    pub fn initialize_fixed_week(year: i32, month: u32, days: &[u32], hours: i64) -> WorkWeek {
+        let mut week = WorkWeek::new();
+        for day in days {
+            let start = Utc.with_ymd_and_hms(year, month, *day, 8, 0, 0).unwrap();
+            let journey = WorkJourney::new(start, start + TimeDelta::hours(hours)).unwrap();
+            week.append_day(
+                &WorkDay::new(&[journey], &DayPolicy::default()).unwrap(),
+                &WeekPolicy::default(),
+            );
+        }
+        week
     }
 
     pub fn intialize_mock_week() -> WorkWeek {
@@ -135,11 +606,11 @@ pub mod tests {
         let (_now, _now_plus_eightt, work_day_five) = initialize_mock_day();
 
         let mut _new_work_week = WorkWeek::new();
-        _new_work_week.append_day(&work_day_one);
-        _new_work_week.append_day(&work_day_two);
-        _new_work_week.append_day(&work_day_three);
-        _new_work_week.append_day(&work_day_four);
-        _new_work_week.append_day(&work_day_five);
+        _new_work_week.append_day(&work_day_one, &WeekPolicy::default());
+        _new_work_week.append_day(&work_day_two, &WeekPolicy::default());
+        _new_work_week.append_day(&work_day_three, &WeekPolicy::default());
+        _new_work_week.append_day(&work_day_four, &WeekPolicy::default());
+        _new_work_week.append_day(&work_day_five, &WeekPolicy::default());
         _new_work_week
     }
 }